@@ -0,0 +1,3 @@
+DROP INDEX idx_bookmark_tags_tag_id;
+DROP TABLE bookmark_tags;
+DROP TABLE tags;
@@ -0,0 +1,2 @@
+ALTER TABLE bookmarks DROP COLUMN position;
+ALTER TABLE folders DROP COLUMN position;
@@ -0,0 +1,16 @@
+ALTER TABLE folders ADD COLUMN position INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE bookmarks ADD COLUMN position INTEGER NOT NULL DEFAULT 0;
+
+-- Start from the alphabetical order the tree showed before positions existed
+UPDATE folders SET position = (
+    SELECT COUNT(*) FROM folders AS sibling
+    WHERE sibling.parent_id IS folders.parent_id
+    AND (lower(sibling.name) < lower(folders.name)
+        OR (lower(sibling.name) = lower(folders.name) AND sibling.id < folders.id))
+);
+UPDATE bookmarks SET position = (
+    SELECT COUNT(*) FROM bookmarks AS sibling
+    WHERE sibling.folder_id IS bookmarks.folder_id
+    AND (lower(sibling.name) < lower(bookmarks.name)
+        OR (lower(sibling.name) = lower(bookmarks.name) AND sibling.id < bookmarks.id))
+);
@@ -0,0 +1,2 @@
+ALTER TABLE bookmarks DROP COLUMN favorite_position;
+ALTER TABLE folders DROP COLUMN favorite_position;
@@ -0,0 +1,8 @@
+ALTER TABLE folders ADD COLUMN favorite_position INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE bookmarks ADD COLUMN favorite_position INTEGER NOT NULL DEFAULT 0;
+
+-- Existing favorites keep the order they were created in
+UPDATE bookmarks SET favorite_position = (
+    SELECT COUNT(*) FROM bookmarks AS other
+    WHERE other.favorite AND other.id < bookmarks.id
+) WHERE favorite;
@@ -0,0 +1,2 @@
+ALTER TABLE bookmarks DROP COLUMN deleted_at;
+ALTER TABLE folders DROP COLUMN deleted_at;
@@ -0,0 +1,2 @@
+ALTER TABLE folders ADD COLUMN deleted_at TIMESTAMP WITH TIME ZONE;
+ALTER TABLE bookmarks ADD COLUMN deleted_at TIMESTAMP WITH TIME ZONE;
@@ -0,0 +1,2 @@
+DROP INDEX idx_history_item;
+DROP TABLE history;
@@ -0,0 +1,2 @@
+ALTER TABLE bookmarks DROP COLUMN last_visited;
+ALTER TABLE bookmarks DROP COLUMN visit_count;
@@ -0,0 +1,2 @@
+ALTER TABLE bookmarks ADD COLUMN visit_count INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE bookmarks ADD COLUMN last_visited TIMESTAMP WITH TIME ZONE;
@@ -0,0 +1,2 @@
+ALTER TABLE bookmarks DROP COLUMN read_at;
+ALTER TABLE bookmarks DROP COLUMN read_later;
@@ -0,0 +1,2 @@
+ALTER TABLE bookmarks ADD COLUMN read_later BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE bookmarks ADD COLUMN read_at TIMESTAMP WITH TIME ZONE;
@@ -0,0 +1,2 @@
+ALTER TABLE bookmarks DROP COLUMN updated;
+ALTER TABLE folders DROP COLUMN updated;
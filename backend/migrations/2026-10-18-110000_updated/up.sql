@@ -0,0 +1,6 @@
+-- SQLite only allows constant defaults on added columns, existing rows start at their creation time
+ALTER TABLE folders ADD COLUMN updated TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT '1970-01-01 00:00:00+00:00';
+ALTER TABLE bookmarks ADD COLUMN updated TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT '1970-01-01 00:00:00+00:00';
+
+UPDATE folders SET updated = created;
+UPDATE bookmarks SET updated = created;
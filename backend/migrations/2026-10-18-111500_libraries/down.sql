@@ -0,0 +1,7 @@
+DROP INDEX idx_bookmarks_library_id;
+DROP INDEX idx_folders_library_id;
+
+ALTER TABLE bookmarks DROP COLUMN library_id;
+ALTER TABLE folders DROP COLUMN library_id;
+
+DROP TABLE libraries;
@@ -0,0 +1,16 @@
+CREATE TABLE IF NOT EXISTS libraries (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+    created TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Everything that existed before libraries belongs to the default one
+INSERT INTO libraries (id, name) VALUES (1, 'Bookmarks');
+
+-- SQLite can't add a REFERENCES column with a default while foreign keys are enforced,
+-- so the link to libraries is kept by the application
+ALTER TABLE folders ADD COLUMN library_id INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE bookmarks ADD COLUMN library_id INTEGER NOT NULL DEFAULT 1;
+
+CREATE INDEX idx_folders_library_id ON folders(library_id);
+CREATE INDEX idx_bookmarks_library_id ON bookmarks(library_id);
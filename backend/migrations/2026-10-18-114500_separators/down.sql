@@ -0,0 +1,3 @@
+DROP INDEX idx_separators_library_id;
+DROP INDEX idx_separators_folder_id;
+DROP TABLE separators;
@@ -0,0 +1,11 @@
+-- Separators take up a slot in their folder's bookmark positions, so formats that have them
+-- (like XBEL) can be exported the way they were imported
+CREATE TABLE IF NOT EXISTS separators (
+    id INTEGER PRIMARY KEY,
+    folder_id INTEGER REFERENCES folders(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL DEFAULT 0,
+    library_id INTEGER NOT NULL DEFAULT 1
+);
+
+CREATE INDEX idx_separators_folder_id ON separators(folder_id);
+CREATE INDEX idx_separators_library_id ON separators(library_id);
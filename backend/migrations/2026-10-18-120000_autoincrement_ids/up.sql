@@ -0,0 +1,76 @@
+-- History is kept per item id, so an id must never be handed out again once its item is
+-- purged. SQLite only guarantees that with AUTOINCREMENT, which means rebuilding both tables.
+-- Foreign keys are off while the old tables are dropped, or the drop would cascade.
+PRAGMA foreign_keys = OFF;
+BEGIN;
+
+CREATE TABLE folders_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    created TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    parent_id INTEGER REFERENCES folders(id) ON DELETE CASCADE,
+    favorite BOOLEAN NOT NULL DEFAULT FALSE,
+    position INTEGER NOT NULL DEFAULT 0,
+    favorite_position INTEGER NOT NULL DEFAULT 0,
+    deleted_at TIMESTAMP WITH TIME ZONE,
+    updated TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT '1970-01-01 00:00:00+00:00',
+    library_id INTEGER NOT NULL DEFAULT 1
+);
+INSERT INTO folders_new (id, name, created, parent_id, favorite, position, favorite_position,
+    deleted_at, updated, library_id)
+SELECT id, name, created, parent_id, favorite, position, favorite_position,
+    deleted_at, updated, library_id
+FROM folders;
+DROP TABLE folders;
+ALTER TABLE folders_new RENAME TO folders;
+CREATE INDEX idx_folders_parent_id ON folders(parent_id);
+CREATE INDEX idx_folders_library_id ON folders(library_id);
+
+CREATE TABLE bookmarks_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    url TEXT NOT NULL,
+    favicon TEXT,
+    favicon_url TEXT,
+    created TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    folder_id INTEGER REFERENCES folders(id) ON DELETE CASCADE,
+    favorite BOOLEAN NOT NULL DEFAULT FALSE,
+    description TEXT,
+    position INTEGER NOT NULL DEFAULT 0,
+    favorite_position INTEGER NOT NULL DEFAULT 0,
+    deleted_at TIMESTAMP WITH TIME ZONE,
+    visit_count INTEGER NOT NULL DEFAULT 0,
+    last_visited TIMESTAMP WITH TIME ZONE,
+    read_later BOOLEAN NOT NULL DEFAULT FALSE,
+    read_at TIMESTAMP WITH TIME ZONE,
+    updated TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT '1970-01-01 00:00:00+00:00',
+    library_id INTEGER NOT NULL DEFAULT 1,
+    keyword TEXT
+);
+INSERT INTO bookmarks_new (id, name, url, favicon, favicon_url, created, folder_id, favorite,
+    description, position, favorite_position, deleted_at, visit_count, last_visited,
+    read_later, read_at, updated, library_id, keyword)
+SELECT id, name, url, favicon, favicon_url, created, folder_id, favorite,
+    description, position, favorite_position, deleted_at, visit_count, last_visited,
+    read_later, read_at, updated, library_id, keyword
+FROM bookmarks;
+DROP TABLE bookmarks;
+ALTER TABLE bookmarks_new RENAME TO bookmarks;
+CREATE INDEX idx_bookmarks_folder_id ON bookmarks(folder_id);
+CREATE INDEX idx_bookmarks_library_id ON bookmarks(library_id);
+
+-- Ids of items that were already purged are in the history, new items start after those too
+DELETE FROM sqlite_sequence WHERE name IN ('folders', 'bookmarks');
+INSERT INTO sqlite_sequence (name, seq)
+SELECT 'folders', COALESCE(MAX(id), 0) FROM (
+    SELECT id FROM folders
+    UNION ALL SELECT item_id FROM history WHERE item_type = 'folder'
+);
+INSERT INTO sqlite_sequence (name, seq)
+SELECT 'bookmarks', COALESCE(MAX(id), 0) FROM (
+    SELECT id FROM bookmarks
+    UNION ALL SELECT item_id FROM history WHERE item_type = 'bookmark'
+);
+
+COMMIT;
+PRAGMA foreign_keys = ON;
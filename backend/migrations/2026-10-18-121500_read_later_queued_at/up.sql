@@ -0,0 +1,4 @@
+ALTER TABLE bookmarks ADD COLUMN queued_at TIMESTAMP WITH TIME ZONE;
+
+-- Items already in the queue keep the order they had, by creation time
+UPDATE bookmarks SET queued_at = created WHERE read_later;
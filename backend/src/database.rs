@@ -1,10 +1,15 @@
 use crate::models::{
-    Bookmark, Folder, NewBookmark, NewFolder, UpdateBookmarkRequest, UpdateFolderRequest,
+    Bookmark, BookmarkNode, BookmarkTag, FavoriteRef, Folder, HistoryAction, HistoryEntry,
+    ItemType, Library, NewBookmark, NewBookmarkRequest, NewFolder, NewHistoryEntry, NewLibrary,
+    NewSeparator, NewTag, Separator, Tag, TagSummary, TrashItems, UpdateBookmarkRequest,
+    UpdateFolderRequest, DEFAULT_LIBRARY_ID,
 };
+use diesel::connection::SimpleConnection;
 use diesel::r2d2::{self, ConnectionManager};
-use diesel::{dsl::not, prelude::*, result::Error, sql_query, ExpressionMethods, SqliteConnection};
+use diesel::{dsl::not, prelude::*, result::Error, ExpressionMethods, SqliteConnection};
 use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
 use directories::ProjectDirs;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::PathBuf;
 
@@ -13,6 +18,23 @@ const MIGRATIONS: EmbeddedMigrations = embed_migrations!();
 pub type Pool = r2d2::Pool<ConnectionManager<SqliteConnection>>;
 pub type DbConnection = r2d2::PooledConnection<ConnectionManager<SqliteConnection>>;
 
+// PRAGMAs are per connection, so they have to run on every connection the pool opens
+#[derive(Debug)]
+struct ConnectionOptions;
+
+impl r2d2::CustomizeConnection<SqliteConnection, r2d2::Error> for ConnectionOptions {
+    fn on_acquire(&self, connection: &mut SqliteConnection) -> Result<(), r2d2::Error> {
+        connection
+            .batch_execute(
+                "PRAGMA foreign_keys = ON;
+                PRAGMA journal_mode = WAL;
+                PRAGMA synchronous = NORMAL;
+                PRAGMA busy_timeout = 5000;",
+            )
+            .map_err(r2d2::Error::QueryError)
+    }
+}
+
 fn running_in_container() -> bool {
     // Check for .dockerenv file (Docker)
     if std::path::Path::new("/.dockerenv").exists() {
@@ -57,27 +79,11 @@ pub fn establish_connection_pool() -> Pool {
     let database_url = get_default_database_path();
     let manager = ConnectionManager::<SqliteConnection>::new(database_url);
 
-    let pool = r2d2::Pool::builder()
+    r2d2::Pool::builder()
         .max_size(15)
+        .connection_customizer(Box::new(ConnectionOptions))
         .build(manager)
-        .expect("Failed to create pool");
-
-    // Initialize the database connection with your PRAGMAs
-    let connection = &mut pool.get().expect("Failed to get connection from pool");
-    sql_query("PRAGMA foreign_keys = ON")
-        .execute(connection)
-        .unwrap();
-    sql_query("PRAGMA journal_mode = WAL")
-        .execute(connection)
-        .unwrap();
-    sql_query("PRAGMA synchronous = NORMAL")
-        .execute(connection)
-        .unwrap();
-    sql_query("PRAGMA busy_timeout = 5000")
-        .execute(connection)
-        .unwrap();
-
-    pool
+        .expect("Failed to create pool")
 }
 
 pub fn initialize_database() -> Pool {
@@ -105,67 +111,248 @@ pub fn initialize_database() -> Pool {
 
 pub fn create_new_folder(
     connection: &mut DbConnection,
+    library_id: i32,
     name: String,
     parent_id: Option<i32>,
 ) -> Result<i32, Error> {
     use crate::schema::folders::{self, id};
-    diesel::insert_into(folders::table)
-        .values(NewFolder {
-            name,
-            created: time::OffsetDateTime::now_local().unwrap_or(time::OffsetDateTime::now_utc()),
-            parent_id,
-            favorite: false,
-        })
-        .returning(id)
-        .get_result(connection)
+    connection.transaction(|connection| {
+        let library_id = resolve_library(connection, library_id, parent_id)?;
+        let position = next_folder_position(connection, library_id, parent_id)?;
+        let now = time::OffsetDateTime::now_local().unwrap_or(time::OffsetDateTime::now_utc());
+        let folder_id = diesel::insert_into(folders::table)
+            .values(NewFolder {
+                name,
+                created: now,
+                parent_id,
+                favorite: false,
+                position,
+                favorite_position: 0,
+                updated: now,
+                library_id,
+            })
+            .returning(id)
+            .get_result(connection)?;
+        let after = snapshot(connection, ItemType::Folder, folder_id)?;
+        record_history(
+            connection,
+            ItemType::Folder,
+            folder_id,
+            HistoryAction::Create,
+            None,
+            after,
+        )?;
+        Ok(folder_id)
+    })
 }
 
 pub fn create_new_bookmark(
     connection: &mut DbConnection,
-    name: String,
-    url: String,
-    folder_id: Option<i32>,
+    bookmark: NewBookmarkRequest,
 ) -> Result<i32, Error> {
     use crate::schema::bookmarks::{self, id};
-    diesel::insert_into(bookmarks::table)
-        .values(NewBookmark {
-            name,
-            url,
-            favicon: None,
-            favicon_url: None,
-            created: time::OffsetDateTime::now_local().unwrap_or(time::OffsetDateTime::now_utc()),
-            folder_id,
-            favorite: false,
-        })
-        .returning(id)
-        .get_result(connection)
+    connection.transaction(|connection| {
+        let library_id = resolve_library(connection, bookmark.library_id, bookmark.folder_id)?;
+        let position = next_bookmark_position(connection, library_id, bookmark.folder_id)?;
+        let now = time::OffsetDateTime::now_local().unwrap_or(time::OffsetDateTime::now_utc());
+        let bookmark_id = diesel::insert_into(bookmarks::table)
+            .values(NewBookmark {
+                name: bookmark.name,
+                url: bookmark.url,
+                favicon: None,
+                favicon_url: None,
+                created: now,
+                folder_id: bookmark.folder_id,
+                favorite: false,
+                description: bookmark.description.filter(|text| !text.trim().is_empty()),
+                position,
+                favorite_position: 0,
+                read_later: bookmark.read_later,
+                read_at: None,
+                updated: now,
+                library_id,
+                keyword: None,
+                queued_at: bookmark.read_later.then_some(now),
+            })
+            .returning(id)
+            .get_result(connection)?;
+        set_bookmark_tags(connection, bookmark_id, bookmark.tags)?;
+        let after = snapshot(connection, ItemType::Bookmark, bookmark_id)?;
+        record_history(
+            connection,
+            ItemType::Bookmark,
+            bookmark_id,
+            HistoryAction::Create,
+            None,
+            after,
+        )?;
+        Ok(bookmark_id)
+    })
 }
 
-pub fn insert_folders(connection: &mut DbConnection, folders: Vec<Folder>) -> Result<usize, Error> {
-    use crate::schema::folders;
-    diesel::insert_into(folders::table)
-        .values(&folders)
-        .execute(connection)
+/// Imported items carry temporary negative ids, the database gives them real ones.
+/// Parents go in before their children so each folder's parent id can be resolved first.
+/// Returns the real id for each temporary one.
+pub fn insert_folders(
+    connection: &mut DbConnection,
+    folders: Vec<Folder>,
+) -> Result<HashMap<i32, i32>, Error> {
+    use crate::schema::{folders, history};
+    connection.transaction(|connection| {
+        let mut new_ids: HashMap<i32, i32> = HashMap::new();
+        let mut entries = Vec::new();
+        let mut pending = folders;
+        while !pending.is_empty() {
+            let (ready, waiting): (Vec<Folder>, Vec<Folder>) =
+                pending.into_iter().partition(|folder| {
+                    folder
+                        .parent_id
+                        .is_none_or(|parent_id| parent_id > 0 || new_ids.contains_key(&parent_id))
+                });
+            // A parent that isn't in the import would leave the folder nowhere
+            if ready.is_empty() {
+                return Err(Error::NotFound);
+            }
+            for mut folder in ready {
+                let temporary_id = folder.id;
+                folder.parent_id = folder
+                    .parent_id
+                    .map(|parent_id| resolve_id(&new_ids, parent_id));
+                folder.id = diesel::insert_into(folders::table)
+                    .values(NewFolder {
+                        name: folder.name.clone(),
+                        created: folder.created,
+                        parent_id: folder.parent_id,
+                        favorite: folder.favorite,
+                        position: folder.position,
+                        favorite_position: folder.favorite_position,
+                        updated: folder.updated,
+                        library_id: folder.library_id,
+                    })
+                    .returning(folders::id)
+                    .get_result(connection)?;
+                new_ids.insert(temporary_id, folder.id);
+                entries.push(import_history_entry(ItemType::Folder, folder.id, &folder)?);
+            }
+            pending = waiting;
+        }
+        diesel::insert_into(history::table)
+            .values(&entries)
+            .execute(connection)?;
+        Ok(new_ids)
+    })
 }
 
+/// Returns the real id for each temporary one, like `insert_folders`
 pub fn insert_bookmarks(
     connection: &mut DbConnection,
     bookmarks: Vec<Bookmark>,
-) -> Result<usize, Error> {
-    use crate::schema::bookmarks;
-    diesel::insert_into(bookmarks::table)
-        .values(&bookmarks)
-        .execute(connection)
+    folder_ids: &HashMap<i32, i32>,
+) -> Result<HashMap<i32, i32>, Error> {
+    use crate::schema::{bookmarks, history};
+    connection.transaction(|connection| {
+        let mut new_ids: HashMap<i32, i32> = HashMap::new();
+        let mut entries = Vec::new();
+        for mut bookmark in bookmarks {
+            let temporary_id = bookmark.id;
+            bookmark.folder_id = bookmark
+                .folder_id
+                .map(|folder_id| resolve_id(folder_ids, folder_id));
+            bookmark.id = diesel::insert_into(bookmarks::table)
+                .values(NewBookmark {
+                    name: bookmark.name.clone(),
+                    url: bookmark.url.clone(),
+                    favicon: bookmark.favicon.clone(),
+                    favicon_url: bookmark.favicon_url.clone(),
+                    created: bookmark.created,
+                    folder_id: bookmark.folder_id,
+                    favorite: bookmark.favorite,
+                    description: bookmark.description.clone(),
+                    position: bookmark.position,
+                    favorite_position: bookmark.favorite_position,
+                    read_later: bookmark.read_later,
+                    read_at: bookmark.read_at,
+                    updated: bookmark.updated,
+                    library_id: bookmark.library_id,
+                    keyword: bookmark.keyword.clone(),
+                    queued_at: bookmark.queued_at,
+                })
+                .returning(bookmarks::id)
+                .get_result(connection)?;
+            new_ids.insert(temporary_id, bookmark.id);
+            entries.push(import_history_entry(
+                ItemType::Bookmark,
+                bookmark.id,
+                &bookmark,
+            )?);
+        }
+        diesel::insert_into(history::table)
+            .values(&entries)
+            .execute(connection)?;
+        Ok(new_ids)
+    })
+}
+
+/// Existing items keep their id, temporary ones get the id they were inserted with
+fn resolve_id(new_ids: &HashMap<i32, i32>, item_id: i32) -> i32 {
+    new_ids.get(&item_id).copied().unwrap_or(item_id)
+}
+
+/// Inserts everything an importer parsed, or nothing if any part fails
+pub fn insert_import(
+    connection: &mut DbConnection,
+    folders: Vec<Folder>,
+    bookmarks: Vec<Bookmark>,
+    bookmark_tags: HashMap<i32, Vec<String>>,
+    new_separators: Vec<NewSeparator>,
+) -> Result<(), Error> {
+    use crate::schema::separators;
+    connection.transaction(|connection| {
+        let folder_ids = insert_folders(connection, folders)?;
+        let bookmark_ids = insert_bookmarks(connection, bookmarks, &folder_ids)?;
+        insert_bookmark_tags(
+            connection,
+            bookmark_tags
+                .into_iter()
+                .map(|(bookmark_id, tags)| (resolve_id(&bookmark_ids, bookmark_id), tags))
+                .collect(),
+        )?;
+        let new_separators: Vec<NewSeparator> = new_separators
+            .into_iter()
+            .map(|separator| NewSeparator {
+                folder_id: separator
+                    .folder_id
+                    .map(|folder_id| resolve_id(&folder_ids, folder_id)),
+                ..separator
+            })
+            .collect();
+        diesel::insert_into(separators::table)
+            .values(&new_separators)
+            .execute(connection)?;
+        Ok(())
+    })
 }
 
-pub fn get_all_folders(connection: &mut DbConnection) -> Result<Vec<Folder>, Error> {
+pub fn get_all_folders(
+    connection: &mut DbConnection,
+    selected_library_id: i32,
+) -> Result<Vec<Folder>, Error> {
     use crate::schema::folders::dsl::*;
-    folders.load(connection)
+    folders
+        .filter(library_id.eq(selected_library_id))
+        .filter(deleted_at.is_null())
+        .load(connection)
 }
 
-pub fn get_all_bookmarks(connection: &mut DbConnection) -> Result<Vec<Bookmark>, Error> {
+pub fn get_all_bookmarks(
+    connection: &mut DbConnection,
+    selected_library_id: i32,
+) -> Result<Vec<Bookmark>, Error> {
     use crate::schema::bookmarks::dsl::*;
-    bookmarks.load(connection)
+    bookmarks
+        .filter(library_id.eq(selected_library_id))
+        .filter(deleted_at.is_null())
+        .load(connection)
 }
 
 pub fn update_folder(
@@ -173,9 +360,18 @@ pub fn update_folder(
     folder: UpdateFolderRequest,
 ) -> Result<usize, Error> {
     use crate::schema::folders::dsl::*;
-    diesel::update(folders.find(folder.id))
-        .set((parent_id.eq(folder.parent_id), name.eq(folder.name)))
-        .execute(connection)
+    with_history(
+        connection,
+        ItemType::Folder,
+        folder.id,
+        HistoryAction::Update,
+        |connection| {
+            place_folder(connection, folder.id, None, folder.parent_id, None)?;
+            diesel::update(folders.find(folder.id))
+                .set(name.eq(folder.name))
+                .execute(connection)
+        },
+    )
 }
 
 pub fn update_bookmark(
@@ -183,55 +379,767 @@ pub fn update_bookmark(
     bookmark: UpdateBookmarkRequest,
 ) -> Result<usize, Error> {
     use crate::schema::bookmarks::dsl::*;
-    diesel::update(bookmarks.find(bookmark.id))
+    with_history(
+        connection,
+        ItemType::Bookmark,
+        bookmark.id,
+        HistoryAction::Update,
+        |connection| {
+            place_bookmark(connection, bookmark.id, None, bookmark.folder_id, None)?;
+            let changed = diesel::update(bookmarks.find(bookmark.id))
+                .set((name.eq(bookmark.name), url.eq(bookmark.url)))
+                .execute(connection)?;
+            // Description and tags are only replaced when the request includes them
+            if let Some(new_description) = bookmark.description {
+                diesel::update(bookmarks.find(bookmark.id))
+                    .set(
+                        description
+                            .eq(Some(new_description).filter(|text| !text.trim().is_empty())),
+                    )
+                    .execute(connection)?;
+            }
+            if let Some(new_tags) = bookmark.tags {
+                set_bookmark_tags(connection, bookmark.id, new_tags)?;
+            }
+            Ok(changed)
+        },
+    )
+}
+
+/// Moves a folder to the trash together with everything inside it that isn't already there
+pub fn delete_folder(connection: &mut DbConnection, folder_id: i32) -> Result<usize, Error> {
+    with_history(
+        connection,
+        ItemType::Folder,
+        folder_id,
+        HistoryAction::Delete,
+        |connection| trash_folder(connection, folder_id),
+    )
+}
+
+fn trash_folder(connection: &mut DbConnection, folder_id: i32) -> Result<usize, Error> {
+    use crate::schema::{bookmarks, folders};
+    let now = time::OffsetDateTime::now_local().unwrap_or(time::OffsetDateTime::now_utc());
+    let mut folder_ids = get_descendant_folder_ids(connection, folder_id)?;
+    folder_ids.push(folder_id);
+
+    let deleted = diesel::update(
+        folders::table
+            .filter(folders::id.eq_any(&folder_ids))
+            .filter(folders::deleted_at.is_null()),
+    )
+    .set(folders::deleted_at.eq(now))
+    .execute(connection)?;
+    diesel::update(
+        bookmarks::table
+            .filter(bookmarks::folder_id.eq_any(&folder_ids))
+            .filter(bookmarks::deleted_at.is_null()),
+    )
+    .set(bookmarks::deleted_at.eq(now))
+    .execute(connection)?;
+
+    Ok(deleted)
+}
+
+/// Moves everything in a folder, or at the top level of a library, to the trash, for imports
+/// that replace what's there
+pub fn trash_library_items(
+    connection: &mut DbConnection,
+    selected_library_id: i32,
+    parent_folder_id: Option<i32>,
+) -> Result<(), Error> {
+    use crate::schema::separators;
+    for folder in get_all_child_folders(connection, selected_library_id, &parent_folder_id)? {
+        delete_folder(connection, folder.id)?;
+    }
+    for bookmark in get_all_child_bookmarks(connection, selected_library_id, &parent_folder_id)? {
+        delete_bookmark(connection, bookmark.id)?;
+    }
+    // Separators in trashed folders stay with them, only the ones next to the bookmarks go
+    let mut query = diesel::delete(separators::table)
+        .filter(separators::library_id.eq(selected_library_id))
+        .into_boxed();
+    query = match parent_folder_id {
+        Some(parent_folder) => query.filter(separators::folder_id.eq(parent_folder)),
+        None => query.filter(separators::folder_id.is_null()),
+    };
+    query.execute(connection)?;
+    Ok(())
+}
+
+pub fn folder_in_library(
+    connection: &mut DbConnection,
+    folder_id: i32,
+    selected_library_id: i32,
+) -> Result<bool, Error> {
+    use crate::schema::folders;
+    let found: Option<i32> = folders::table
+        .select(folders::id)
+        .filter(folders::id.eq(folder_id))
+        .filter(folders::library_id.eq(selected_library_id))
+        .filter(folders::deleted_at.is_null())
+        .first(connection)
+        .optional()?;
+    Ok(found.is_some())
+}
+
+/// Moves a bookmark to the trash
+pub fn delete_bookmark(connection: &mut DbConnection, bookmark_id: i32) -> Result<usize, Error> {
+    with_history(
+        connection,
+        ItemType::Bookmark,
+        bookmark_id,
+        HistoryAction::Delete,
+        |connection| trash_bookmark(connection, bookmark_id),
+    )
+}
+
+fn trash_bookmark(connection: &mut DbConnection, bookmark_id: i32) -> Result<usize, Error> {
+    use crate::schema::bookmarks::dsl::*;
+    let now = time::OffsetDateTime::now_local().unwrap_or(time::OffsetDateTime::now_utc());
+    diesel::update(bookmarks.find(bookmark_id).filter(deleted_at.is_null()))
+        .set(deleted_at.eq(now))
+        .execute(connection)
+}
+
+/// Ids of every folder below `folder_id`, at any depth
+fn get_descendant_folder_ids(
+    connection: &mut DbConnection,
+    folder_id: i32,
+) -> Result<Vec<i32>, Error> {
+    use crate::schema::folders::dsl::*;
+    let mut descendant_ids: Vec<i32> = Vec::new();
+    let mut parent_ids = vec![folder_id];
+
+    while !parent_ids.is_empty() {
+        let child_ids: Vec<i32> = folders
+            .select(id)
+            .filter(parent_id.eq_any(&parent_ids))
+            .load(connection)?;
+        descendant_ids.extend(&child_ids);
+        parent_ids = child_ids;
+    }
+
+    Ok(descendant_ids)
+}
+
+pub fn get_trash(connection: &mut DbConnection, library_id: i32) -> Result<TrashItems, Error> {
+    use crate::schema::{bookmarks, folders};
+    let trashed_folders: Vec<Folder> = folders::table
+        .filter(folders::deleted_at.is_not_null())
+        .filter(folders::library_id.eq(library_id))
+        .load(connection)?;
+    let trashed_bookmarks: Vec<Bookmark> = bookmarks::table
+        .filter(bookmarks::deleted_at.is_not_null())
+        .filter(bookmarks::library_id.eq(library_id))
+        .load(connection)?;
+
+    // Children that went to the trash with their folder are restored with it, so hide them
+    let deletions: HashMap<i32, Option<time::OffsetDateTime>> = trashed_folders
+        .iter()
+        .map(|folder| (folder.id, folder.deleted_at))
+        .collect();
+    let deleted_with_parent = |parent: Option<i32>, deleted: Option<time::OffsetDateTime>| {
+        parent.is_some_and(|parent| deletions.get(&parent) == Some(&deleted))
+    };
+
+    let mut trash = TrashItems {
+        folders: trashed_folders
+            .iter()
+            .filter(|folder| !deleted_with_parent(folder.parent_id, folder.deleted_at))
+            .cloned()
+            .collect(),
+        bookmarks: trashed_bookmarks
+            .into_iter()
+            .filter(|bookmark| !deleted_with_parent(bookmark.folder_id, bookmark.deleted_at))
+            .collect(),
+    };
+    // Most recently deleted first
+    trash
+        .folders
+        .sort_by_key(|folder| std::cmp::Reverse(folder.deleted_at));
+    trash
+        .bookmarks
+        .sort_by_key(|bookmark| std::cmp::Reverse(bookmark.deleted_at));
+
+    Ok(trash)
+}
+
+/// Brings a folder back together with everything that was trashed along with it.
+/// If its parent is still in the trash (or gone), the folder is restored to the top level.
+pub fn restore_folder(connection: &mut DbConnection, folder_id: i32) -> Result<usize, Error> {
+    with_history(
+        connection,
+        ItemType::Folder,
+        folder_id,
+        HistoryAction::Restore,
+        |connection| untrash_folder(connection, folder_id),
+    )
+}
+
+fn untrash_folder(connection: &mut DbConnection, folder_id: i32) -> Result<usize, Error> {
+    use crate::schema::{bookmarks, folders};
+    let folder: Folder = folders::table.find(folder_id).first(connection)?;
+    let Some(deleted) = folder.deleted_at else {
+        return Ok(0);
+    };
+
+    let restored_parent_id = match folder.parent_id {
+        Some(parent) if is_live_folder(connection, parent)? => Some(parent),
+        _ => None,
+    };
+    let new_position = next_folder_position(connection, folder.library_id, restored_parent_id)?;
+    diesel::update(folders::table.find(folder_id))
+        .set((
+            folders::parent_id.eq(restored_parent_id),
+            folders::position.eq(new_position),
+        ))
+        .execute(connection)?;
+
+    let mut folder_ids = get_descendant_folder_ids(connection, folder_id)?;
+    folder_ids.push(folder_id);
+
+    let restored = diesel::update(
+        folders::table
+            .filter(folders::id.eq_any(&folder_ids))
+            .filter(folders::deleted_at.eq(deleted)),
+    )
+    .set(folders::deleted_at.eq(None::<time::OffsetDateTime>))
+    .execute(connection)?;
+    diesel::update(
+        bookmarks::table
+            .filter(bookmarks::folder_id.eq_any(&folder_ids))
+            .filter(bookmarks::deleted_at.eq(deleted)),
+    )
+    .set(bookmarks::deleted_at.eq(None::<time::OffsetDateTime>))
+    .execute(connection)?;
+
+    Ok(restored)
+}
+
+/// Brings a bookmark back into its folder, or the top level if that folder is still in the trash
+pub fn restore_bookmark(connection: &mut DbConnection, bookmark_id: i32) -> Result<usize, Error> {
+    with_history(
+        connection,
+        ItemType::Bookmark,
+        bookmark_id,
+        HistoryAction::Restore,
+        |connection| untrash_bookmark(connection, bookmark_id),
+    )
+}
+
+fn untrash_bookmark(connection: &mut DbConnection, bookmark_id: i32) -> Result<usize, Error> {
+    use crate::schema::bookmarks::dsl::*;
+    let bookmark: Bookmark = bookmarks.find(bookmark_id).first(connection)?;
+    if bookmark.deleted_at.is_none() {
+        return Ok(0);
+    }
+
+    let restored_folder_id = match bookmark.folder_id {
+        Some(parent) if is_live_folder(connection, parent)? => Some(parent),
+        _ => None,
+    };
+    let new_position = next_bookmark_position(connection, bookmark.library_id, restored_folder_id)?;
+    diesel::update(bookmarks.find(bookmark_id))
         .set((
-            name.eq(bookmark.name),
-            url.eq(bookmark.url),
-            folder_id.eq(bookmark.folder_id),
+            folder_id.eq(restored_folder_id),
+            position.eq(new_position),
+            deleted_at.eq(None::<time::OffsetDateTime>),
         ))
         .execute(connection)
 }
 
-pub fn delete_folder(connection: &mut DbConnection, folder_id: i32) -> Result<usize, Error> {
+fn is_live_folder(connection: &mut DbConnection, folder_id: i32) -> Result<bool, Error> {
     use crate::schema::folders::dsl::*;
-    diesel::delete(folders.filter(id.eq(folder_id))).execute(connection)
+    let folder_deleted_at: Option<Option<time::OffsetDateTime>> = folders
+        .select(deleted_at)
+        .find(folder_id)
+        .first(connection)
+        .optional()?;
+    Ok(matches!(folder_deleted_at, Some(None)))
 }
 
-pub fn delete_bookmark(connection: &mut DbConnection, bookmark_id: i32) -> Result<usize, Error> {
-    use crate::schema::bookmarks::dsl::*;
-    diesel::delete(bookmarks.filter(id.eq(bookmark_id))).execute(connection)
+/// Permanently deletes trashed items, either all of them or those trashed before `deleted_before`.
+/// Only one library's trash is emptied when `selected_library_id` is given.
+pub fn purge_trash(
+    connection: &mut DbConnection,
+    selected_library_id: Option<i32>,
+    deleted_before: Option<time::OffsetDateTime>,
+) -> Result<usize, Error> {
+    use crate::schema::{bookmarks, folders};
+    connection.transaction(|connection| {
+        let mut folder_query = folders::table
+            .select((folders::id, folders::deleted_at))
+            .filter(folders::deleted_at.is_not_null())
+            .into_boxed();
+        let mut bookmark_query = bookmarks::table
+            .select((bookmarks::id, bookmarks::deleted_at))
+            .filter(bookmarks::deleted_at.is_not_null())
+            .into_boxed();
+        if let Some(selected_library_id) = selected_library_id {
+            folder_query = folder_query.filter(folders::library_id.eq(selected_library_id));
+            bookmark_query = bookmark_query.filter(bookmarks::library_id.eq(selected_library_id));
+        }
+        let trashed_folders: Vec<(i32, Option<time::OffsetDateTime>)> =
+            folder_query.load(connection)?;
+        let trashed_bookmarks: Vec<(i32, Option<time::OffsetDateTime>)> =
+            bookmark_query.load(connection)?;
+
+        // Timestamps are stored with their offsets, so compare them here rather than in SQL
+        let expired = |deleted: &Option<time::OffsetDateTime>| match deleted_before {
+            Some(cutoff) => deleted.is_some_and(|deleted| deleted < cutoff),
+            None => true,
+        };
+        let folder_ids: HashSet<i32> = trashed_folders
+            .into_iter()
+            .filter(|(_, deleted)| expired(deleted))
+            .map(|(folder_id, _)| folder_id)
+            .collect();
+        let bookmark_ids: Vec<i32> = trashed_bookmarks
+            .into_iter()
+            .filter(|(_, deleted)| expired(deleted))
+            .map(|(bookmark_id, _)| bookmark_id)
+            .collect();
+
+        record_purges(connection, ItemType::Folder, folder_ids.iter().copied())?;
+        record_purges(connection, ItemType::Bookmark, bookmark_ids.iter().copied())?;
+
+        // Anything still inside a purged folder goes with it through ON DELETE CASCADE
+        let purged_bookmarks =
+            diesel::delete(bookmarks::table.filter(bookmarks::id.eq_any(&bookmark_ids)))
+                .execute(connection)?;
+        let purged_folders = diesel::delete(folders::table.filter(folders::id.eq_any(folder_ids)))
+            .execute(connection)?;
+
+        Ok(purged_bookmarks + purged_folders)
+    })
 }
 
+/// Keeps the last state of items about to be deleted for good, so they can still be reverted
+fn record_purges(
+    connection: &mut DbConnection,
+    item_type: ItemType,
+    item_ids: impl IntoIterator<Item = i32>,
+) -> Result<(), Error> {
+    for item_id in item_ids {
+        let before = snapshot(connection, item_type, item_id)?;
+        record_history(
+            connection,
+            item_type,
+            item_id,
+            HistoryAction::Purge,
+            before,
+            None,
+        )?;
+    }
+    Ok(())
+}
+
+/// Moves a folder under a new parent and places it at `target_index` among its new siblings.
+/// Without an index the folder goes to the end, or keeps its place if the parent is unchanged.
+/// `new_library_id` only matters for the top level, otherwise the parent's library is used.
 pub fn change_folder_parent(
     connection: &mut DbConnection,
     folder_id: i32,
+    new_library_id: Option<i32>,
+    new_parent_id: Option<i32>,
+    target_index: Option<usize>,
+) -> Result<usize, Error> {
+    with_history(
+        connection,
+        ItemType::Folder,
+        folder_id,
+        HistoryAction::Move,
+        |connection| {
+            place_folder(
+                connection,
+                folder_id,
+                new_library_id,
+                new_parent_id,
+                target_index,
+            )
+        },
+    )
+}
+
+fn place_folder(
+    connection: &mut DbConnection,
+    folder_id: i32,
+    new_library_id: Option<i32>,
     new_parent_id: Option<i32>,
+    target_index: Option<usize>,
 ) -> Result<usize, Error> {
     use crate::schema::folders::dsl::*;
-    diesel::update(folders.find(folder_id))
-        .set(parent_id.eq(new_parent_id))
-        .execute(connection)
+    connection.transaction(|connection| {
+        let (current_parent_id, current_library_id): (Option<i32>, i32) = folders
+            .select((parent_id, library_id))
+            .find(folder_id)
+            .first(connection)?;
+        let target_library_id = resolve_library(
+            connection,
+            new_library_id.unwrap_or(current_library_id),
+            new_parent_id,
+        )?;
+        if current_parent_id == new_parent_id
+            && current_library_id == target_library_id
+            && target_index.is_none()
+        {
+            return Ok(0);
+        }
+
+        let mut query = folders
+            .select(id)
+            .filter(library_id.eq(target_library_id))
+            .filter(deleted_at.is_null())
+            .order((position.asc(), id.asc()))
+            .into_boxed();
+        query = match new_parent_id {
+            Some(new_parent) => query.filter(parent_id.eq(new_parent)),
+            None => query.filter(parent_id.is_null()),
+        };
+        let mut sibling_ids: Vec<i32> = query.load(connection)?;
+        sibling_ids.retain(|sibling_id| *sibling_id != folder_id);
+        let index = target_index
+            .unwrap_or(sibling_ids.len())
+            .min(sibling_ids.len());
+        sibling_ids.insert(index, folder_id);
+
+        let changed = diesel::update(folders.find(folder_id))
+            .set(parent_id.eq(new_parent_id))
+            .execute(connection)?;
+        if target_library_id != current_library_id {
+            move_folder_to_library(connection, folder_id, target_library_id)?;
+        }
+        for (new_position, sibling_id) in sibling_ids.into_iter().enumerate() {
+            diesel::update(folders.find(sibling_id))
+                .set(position.eq(new_position as i32))
+                .execute(connection)?;
+        }
+
+        Ok(changed)
+    })
 }
 
+/// Moves a bookmark into a new folder and places it at `target_index` among its new siblings.
+/// Without an index the bookmark goes to the end, or keeps its place if the folder is unchanged.
+/// `new_library_id` only matters for the top level, otherwise the folder's library is used.
 pub fn change_bookmark_folder(
     connection: &mut DbConnection,
     bookmark_id: i32,
+    new_library_id: Option<i32>,
+    new_folder_id: Option<i32>,
+    target_index: Option<usize>,
+) -> Result<usize, Error> {
+    with_history(
+        connection,
+        ItemType::Bookmark,
+        bookmark_id,
+        HistoryAction::Move,
+        |connection| {
+            place_bookmark(
+                connection,
+                bookmark_id,
+                new_library_id,
+                new_folder_id,
+                target_index,
+            )
+        },
+    )
+}
+
+fn place_bookmark(
+    connection: &mut DbConnection,
+    bookmark_id: i32,
+    new_library_id: Option<i32>,
     new_folder_id: Option<i32>,
+    target_index: Option<usize>,
 ) -> Result<usize, Error> {
     use crate::schema::bookmarks::dsl::*;
-    diesel::update(bookmarks.find(bookmark_id))
-        .set(folder_id.eq(new_folder_id))
-        .execute(connection)
+    connection.transaction(|connection| {
+        let (current_folder_id, current_library_id): (Option<i32>, i32) = bookmarks
+            .select((folder_id, library_id))
+            .find(bookmark_id)
+            .first(connection)?;
+        let target_library_id = resolve_library(
+            connection,
+            new_library_id.unwrap_or(current_library_id),
+            new_folder_id,
+        )?;
+        if current_folder_id == new_folder_id
+            && current_library_id == target_library_id
+            && target_index.is_none()
+        {
+            return Ok(0);
+        }
+
+        let mut query = bookmarks
+            .select((id, position))
+            .filter(library_id.eq(target_library_id))
+            .filter(deleted_at.is_null())
+            .order((position.asc(), id.asc()))
+            .into_boxed();
+        query = match new_folder_id {
+            Some(new_folder) => query.filter(folder_id.eq(new_folder)),
+            None => query.filter(folder_id.is_null()),
+        };
+        let mut siblings: Vec<(i32, i32)> = query.load(connection)?;
+        siblings.retain(|(sibling_id, _)| *sibling_id != bookmark_id);
+        let separators_before =
+            separators_by_bookmark(connection, target_library_id, new_folder_id, &siblings)?;
+        let mut sibling_ids: Vec<i32> = siblings
+            .into_iter()
+            .map(|(sibling_id, _)| sibling_id)
+            .collect();
+        let index = target_index
+            .unwrap_or(sibling_ids.len())
+            .min(sibling_ids.len());
+        sibling_ids.insert(index, bookmark_id);
+
+        let changed = diesel::update(bookmarks.find(bookmark_id))
+            .set((
+                folder_id.eq(new_folder_id),
+                library_id.eq(target_library_id),
+            ))
+            .execute(connection)?;
+        renumber_bookmarks(connection, sibling_ids, separators_before)?;
+
+        Ok(changed)
+    })
+}
+
+/// Separators share their folder's position numbers with its bookmarks and sit before the
+/// bookmark that follows them. They are keyed by that bookmark's id, `None` for those at the end.
+fn separators_by_bookmark(
+    connection: &mut DbConnection,
+    selected_library_id: i32,
+    parent_folder_id: Option<i32>,
+    ordered_bookmarks: &[(i32, i32)],
+) -> Result<HashMap<Option<i32>, Vec<i32>>, Error> {
+    let child_separators =
+        get_all_child_separators(connection, selected_library_id, &parent_folder_id)?;
+    let mut separators_before: HashMap<Option<i32>, Vec<i32>> = HashMap::new();
+    for separator in child_separators {
+        let next_bookmark_id = ordered_bookmarks
+            .iter()
+            .find(|(_, bookmark_position)| separator.position <= *bookmark_position)
+            .map(|(next_bookmark_id, _)| *next_bookmark_id);
+        separators_before
+            .entry(next_bookmark_id)
+            .or_default()
+            .push(separator.id);
+    }
+    Ok(separators_before)
+}
+
+/// Numbers a folder's bookmarks in their new order, with each separator kept right before the
+/// same bookmark as before
+fn renumber_bookmarks(
+    connection: &mut DbConnection,
+    ordered_bookmark_ids: Vec<i32>,
+    mut separators_before: HashMap<Option<i32>, Vec<i32>>,
+) -> Result<(), Error> {
+    use crate::schema::{bookmarks, separators};
+    enum Entry {
+        Bookmark(i32),
+        Separator(i32),
+    }
+
+    let mut entries = Vec::new();
+    for bookmark_id in ordered_bookmark_ids {
+        let separator_ids = separators_before.remove(&Some(bookmark_id));
+        entries.extend(
+            separator_ids
+                .unwrap_or_default()
+                .into_iter()
+                .map(Entry::Separator),
+        );
+        entries.push(Entry::Bookmark(bookmark_id));
+    }
+    let trailing_separator_ids = separators_before.remove(&None);
+    entries.extend(
+        trailing_separator_ids
+            .unwrap_or_default()
+            .into_iter()
+            .map(Entry::Separator),
+    );
+
+    for (new_position, entry) in entries.into_iter().enumerate() {
+        let new_position = new_position as i32;
+        match entry {
+            Entry::Bookmark(bookmark_id) => diesel::update(bookmarks::table.find(bookmark_id))
+                .set(bookmarks::position.eq(new_position))
+                .execute(connection)?,
+            Entry::Separator(separator_id) => diesel::update(separators::table.find(separator_id))
+                .set(separators::position.eq(new_position))
+                .execute(connection)?,
+        };
+    }
+    Ok(())
+}
+
+/// The position just after the last folder inside `parent_folder_id`
+pub fn next_folder_position(
+    connection: &mut DbConnection,
+    selected_library_id: i32,
+    parent_folder_id: Option<i32>,
+) -> Result<i32, Error> {
+    use crate::schema::folders::dsl::*;
+    let mut query = folders
+        .select(diesel::dsl::max(position))
+        .filter(library_id.eq(selected_library_id))
+        .into_boxed();
+    query = match parent_folder_id {
+        Some(parent_folder) => query.filter(parent_id.eq(parent_folder)),
+        None => query.filter(parent_id.is_null()),
+    };
+    Ok(query
+        .first::<Option<i32>>(connection)?
+        .map_or(0, |last| last + 1))
 }
 
+/// The position just after the last bookmark inside `parent_folder_id`
+pub fn next_bookmark_position(
+    connection: &mut DbConnection,
+    selected_library_id: i32,
+    parent_folder_id: Option<i32>,
+) -> Result<i32, Error> {
+    use crate::schema::bookmarks::dsl::*;
+    let mut query = bookmarks
+        .select(diesel::dsl::max(position))
+        .filter(library_id.eq(selected_library_id))
+        .into_boxed();
+    query = match parent_folder_id {
+        Some(parent_folder) => query.filter(folder_id.eq(parent_folder)),
+        None => query.filter(folder_id.is_null()),
+    };
+    Ok(query
+        .first::<Option<i32>>(connection)?
+        .map_or(0, |last| last + 1))
+}
+
+/// New favorites are added to the end of the favorites bar
 pub fn toggle_bookmark_favorite(
     connection: &mut DbConnection,
     bookmark_id: i32,
 ) -> Result<usize, Error> {
     use crate::schema::bookmarks::dsl::*;
-    diesel::update(bookmarks.find(bookmark_id))
-        .set(favorite.eq(not(favorite)))
-        .execute(connection)
+    with_history(
+        connection,
+        ItemType::Bookmark,
+        bookmark_id,
+        HistoryAction::Favorite,
+        |connection| {
+            let bookmark_library_id = bookmarks
+                .find(bookmark_id)
+                .select(library_id)
+                .first(connection)?;
+            let new_favorite_position = next_favorite_position(connection, bookmark_library_id)?;
+            diesel::update(bookmarks.find(bookmark_id))
+                .set((
+                    favorite.eq(not(favorite)),
+                    favorite_position.eq(new_favorite_position),
+                ))
+                .execute(connection)
+        },
+    )
+}
+
+/// New favorites are added to the end of the favorites bar
+pub fn toggle_folder_favorite(
+    connection: &mut DbConnection,
+    folder_id: i32,
+) -> Result<usize, Error> {
+    use crate::schema::folders::dsl::*;
+    with_history(
+        connection,
+        ItemType::Folder,
+        folder_id,
+        HistoryAction::Favorite,
+        |connection| {
+            let folder_library_id = folders
+                .find(folder_id)
+                .select(library_id)
+                .first(connection)?;
+            let new_favorite_position = next_favorite_position(connection, folder_library_id)?;
+            diesel::update(folders.find(folder_id))
+                .set((
+                    favorite.eq(not(favorite)),
+                    favorite_position.eq(new_favorite_position),
+                ))
+                .execute(connection)
+        },
+    )
+}
+
+/// The position just after the last favorite in a library, counting folders and bookmarks together
+pub fn next_favorite_position(
+    connection: &mut DbConnection,
+    library_id: i32,
+) -> Result<i32, Error> {
+    use crate::schema::{bookmarks, folders};
+    let last_folder: Option<i32> = folders::table
+        .filter(folders::favorite.eq(true))
+        .filter(folders::library_id.eq(library_id))
+        .select(diesel::dsl::max(folders::favorite_position))
+        .first(connection)?;
+    let last_bookmark: Option<i32> = bookmarks::table
+        .filter(bookmarks::favorite.eq(true))
+        .filter(bookmarks::library_id.eq(library_id))
+        .select(diesel::dsl::max(bookmarks::favorite_position))
+        .first(connection)?;
+    Ok(last_folder.max(last_bookmark).map_or(0, |last| last + 1))
+}
+
+/// Stores the order of the favorites bar, items not listed keep their old position.
+/// Fails with `NotFound` if an item isn't a favorite of the library, without changing anything.
+pub fn reorder_favorites(
+    connection: &mut DbConnection,
+    selected_library_id: i32,
+    favorites: Vec<FavoriteRef>,
+) -> Result<(), Error> {
+    use crate::schema::{bookmarks, folders};
+    connection.transaction(|connection| {
+        for (new_position, item) in favorites.into_iter().enumerate() {
+            let new_position = new_position as i32;
+            let current_position: Option<i32> = match item.item_type {
+                ItemType::Folder => folders::table
+                    .select(folders::favorite_position)
+                    .find(item.item_id)
+                    .filter(folders::library_id.eq(selected_library_id))
+                    .filter(folders::favorite.eq(true))
+                    .first(connection)
+                    .optional()?,
+                ItemType::Bookmark => bookmarks::table
+                    .select(bookmarks::favorite_position)
+                    .find(item.item_id)
+                    .filter(bookmarks::library_id.eq(selected_library_id))
+                    .filter(bookmarks::favorite.eq(true))
+                    .first(connection)
+                    .optional()?,
+            };
+            // Only items that actually moved count as modified
+            match current_position {
+                None => return Err(Error::NotFound),
+                Some(current_position) if current_position == new_position => continue,
+                Some(_) => {}
+            }
+            with_history(
+                connection,
+                item.item_type,
+                item.item_id,
+                HistoryAction::Favorite,
+                |connection| match item.item_type {
+                    ItemType::Folder => diesel::update(folders::table.find(item.item_id))
+                        .set(folders::favorite_position.eq(new_position))
+                        .execute(connection),
+                    ItemType::Bookmark => diesel::update(bookmarks::table.find(item.item_id))
+                        .set(bookmarks::favorite_position.eq(new_position))
+                        .execute(connection),
+                },
+            )?;
+        }
+        Ok(())
+    })
 }
 
 pub fn is_subfolder(
@@ -257,40 +1165,1059 @@ pub fn is_subfolder(
     false
 }
 
-pub fn get_highest_bookmark_id(connection: &mut DbConnection) -> Result<i32, Error> {
-    use crate::schema::bookmarks::dsl::*;
-    bookmarks.select(id).order(id.desc()).first(connection)
-}
-
-pub fn get_highest_folder_id(connection: &mut DbConnection) -> Result<i32, Error> {
-    use crate::schema::folders::dsl::*;
-    folders.select(id).order(id.desc()).first(connection)
-}
-
 pub fn get_all_child_folders(
     connection: &mut DbConnection,
+    selected_library_id: i32,
     parent_folder_id: &Option<i32>,
 ) -> Result<Vec<Folder>, Error> {
     use crate::schema::folders::dsl::*;
     if parent_folder_id.is_some() {
         folders
             .filter(parent_id.eq(parent_folder_id))
+            .filter(deleted_at.is_null())
+            .order((position.asc(), id.asc()))
             .load(connection)
     } else {
-        folders.filter(parent_id.is_null()).load(connection)
+        folders
+            .filter(parent_id.is_null())
+            .filter(library_id.eq(selected_library_id))
+            .filter(deleted_at.is_null())
+            .order((position.asc(), id.asc()))
+            .load(connection)
     }
 }
 
 pub fn get_all_child_bookmarks(
     connection: &mut DbConnection,
+    selected_library_id: i32,
     parent_folder_id: &Option<i32>,
 ) -> Result<Vec<Bookmark>, Error> {
     use crate::schema::bookmarks::dsl::*;
     if parent_folder_id.is_some() {
         bookmarks
             .filter(folder_id.eq(parent_folder_id))
+            .filter(deleted_at.is_null())
+            .order((position.asc(), id.asc()))
+            .load(connection)
+    } else {
+        bookmarks
+            .filter(folder_id.is_null())
+            .filter(library_id.eq(selected_library_id))
+            .filter(deleted_at.is_null())
+            .order((position.asc(), id.asc()))
+            .load(connection)
+    }
+}
+
+pub fn get_all_child_separators(
+    connection: &mut DbConnection,
+    selected_library_id: i32,
+    parent_folder_id: &Option<i32>,
+) -> Result<Vec<Separator>, Error> {
+    use crate::schema::separators::dsl::*;
+    if parent_folder_id.is_some() {
+        separators
+            .filter(folder_id.eq(parent_folder_id))
+            .order((position.asc(), id.asc()))
+            .load(connection)
+    } else {
+        separators
+            .filter(folder_id.is_null())
+            .filter(library_id.eq(selected_library_id))
+            .order((position.asc(), id.asc()))
             .load(connection)
+    }
+}
+
+/// Tags on bookmarks in a library. Bookmarks in the trash keep their tag listed
+/// but don't count towards it.
+pub fn get_all_tags(
+    connection: &mut DbConnection,
+    library_id: i32,
+) -> Result<Vec<TagSummary>, Error> {
+    use crate::schema::{bookmark_tags, bookmarks, tags};
+    let tagged: Vec<(i32, String, Option<time::OffsetDateTime>)> = tags::table
+        .inner_join(bookmark_tags::table.inner_join(bookmarks::table))
+        .filter(bookmarks::library_id.eq(library_id))
+        .select((tags::id, tags::name, bookmarks::deleted_at))
+        .order((tags::name.asc(), tags::id.asc()))
+        .load(connection)?;
+
+    let mut summaries: Vec<TagSummary> = Vec::new();
+    for (tag_id, tag_name, deleted) in tagged {
+        if summaries.last().is_none_or(|summary| summary.id != tag_id) {
+            summaries.push(TagSummary {
+                id: tag_id,
+                name: tag_name,
+                bookmark_count: 0,
+            });
+        }
+        if deleted.is_none() {
+            if let Some(summary) = summaries.last_mut() {
+                summary.bookmark_count += 1;
+            }
+        }
+    }
+    Ok(summaries)
+}
+
+/// Returns the tag names of every bookmark, keyed by bookmark id
+pub fn get_all_bookmark_tags(
+    connection: &mut DbConnection,
+) -> Result<HashMap<i32, Vec<String>>, Error> {
+    use crate::schema::{bookmark_tags, tags};
+    let rows: Vec<(i32, String)> = bookmark_tags::table
+        .inner_join(tags::table)
+        .select((bookmark_tags::bookmark_id, tags::name))
+        .order(tags::name.asc())
+        .load(connection)?;
+
+    let mut bookmark_tags: HashMap<i32, Vec<String>> = HashMap::new();
+    for (bookmark_id, tag_name) in rows {
+        bookmark_tags.entry(bookmark_id).or_default().push(tag_name);
+    }
+
+    Ok(bookmark_tags)
+}
+
+/// Finds the tag with the given name (case-insensitive) or creates it
+fn get_or_create_tag(connection: &mut SqliteConnection, tag_name: &str) -> Result<i32, Error> {
+    use crate::schema::tags::dsl::*;
+    if let Some(tag) = tags
+        .filter(name.eq(tag_name))
+        .first::<Tag>(connection)
+        .optional()?
+    {
+        return Ok(tag.id);
+    }
+
+    diesel::insert_into(tags)
+        .values(NewTag {
+            name: tag_name.to_string(),
+        })
+        .returning(id)
+        .get_result(connection)
+}
+
+/// Replaces all tags on a bookmark, creating any tags that don't exist yet
+pub fn set_bookmark_tags(
+    connection: &mut SqliteConnection,
+    bookmark_id: i32,
+    tag_names: Vec<String>,
+) -> Result<(), Error> {
+    use crate::schema::bookmark_tags;
+    diesel::delete(bookmark_tags::table.filter(bookmark_tags::bookmark_id.eq(bookmark_id)))
+        .execute(connection)?;
+
+    let mut links: Vec<BookmarkTag> = Vec::new();
+    for tag_name in tag_names {
+        let tag_name = tag_name.trim();
+        if tag_name.is_empty() {
+            continue;
+        }
+        let tag_id = get_or_create_tag(connection, tag_name)?;
+        if !links.iter().any(|link| link.tag_id == tag_id) {
+            links.push(BookmarkTag {
+                bookmark_id,
+                tag_id,
+            });
+        }
+    }
+
+    diesel::insert_into(bookmark_tags::table)
+        .values(&links)
+        .execute(connection)?;
+
+    Ok(())
+}
+
+pub fn insert_bookmark_tags(
+    connection: &mut DbConnection,
+    bookmark_tags: HashMap<i32, Vec<String>>,
+) -> Result<(), Error> {
+    connection.transaction(|connection| {
+        for (bookmark_id, tag_names) in bookmark_tags {
+            set_bookmark_tags(connection, bookmark_id, tag_names)?;
+        }
+        Ok(())
+    })
+}
+
+pub fn rename_tag(
+    connection: &mut DbConnection,
+    tag_id: i32,
+    new_name: &str,
+) -> Result<usize, Error> {
+    use crate::schema::tags::dsl::*;
+    connection.transaction(|connection| {
+        let renamed = diesel::update(tags.find(tag_id))
+            .set(name.eq(new_name.trim()))
+            .execute(connection)?;
+        let bookmark_ids = get_tagged_bookmark_ids(connection, &[tag_id])?;
+        touch(connection, ItemType::Bookmark, &bookmark_ids)?;
+        Ok(renamed)
+    })
+}
+
+/// Moves every bookmark from the source tags onto the target tag and deletes the source tags
+pub fn merge_tags(
+    connection: &mut DbConnection,
+    source_ids: Vec<i32>,
+    target_id: i32,
+) -> Result<(), Error> {
+    use crate::schema::{bookmark_tags, tags};
+    connection.transaction(|connection| {
+        // Make sure the target exists before anything is removed
+        tags::table.find(target_id).first::<Tag>(connection)?;
+
+        let source_ids: Vec<i32> = source_ids
+            .into_iter()
+            .filter(|source_id| *source_id != target_id)
+            .collect();
+
+        let bookmark_ids = get_tagged_bookmark_ids(connection, &source_ids)?;
+        let links: Vec<BookmarkTag> = bookmark_ids
+            .iter()
+            .copied()
+            .map(|bookmark_id| BookmarkTag {
+                bookmark_id,
+                tag_id: target_id,
+            })
+            .collect();
+
+        diesel::insert_or_ignore_into(bookmark_tags::table)
+            .values(&links)
+            .execute(connection)?;
+        diesel::delete(tags::table.filter(tags::id.eq_any(&source_ids))).execute(connection)?;
+        touch(connection, ItemType::Bookmark, &bookmark_ids)?;
+
+        Ok(())
+    })
+}
+
+pub fn delete_tag(connection: &mut DbConnection, tag_id: i32) -> Result<usize, Error> {
+    use crate::schema::tags::dsl::*;
+    connection.transaction(|connection| {
+        let bookmark_ids = get_tagged_bookmark_ids(connection, &[tag_id])?;
+        touch(connection, ItemType::Bookmark, &bookmark_ids)?;
+        diesel::delete(tags.filter(id.eq(tag_id))).execute(connection)
+    })
+}
+
+/// Runs `change` in a transaction and records the item's state before and after it.
+/// Nothing is recorded when the change left the item as it was.
+fn with_history<T>(
+    connection: &mut DbConnection,
+    item_type: ItemType,
+    item_id: i32,
+    action: HistoryAction,
+    change: impl FnOnce(&mut DbConnection) -> Result<T, Error>,
+) -> Result<T, Error> {
+    connection.transaction(|connection| {
+        let before = snapshot(connection, item_type, item_id)?;
+        let result = change(connection)?;
+        let mut after = snapshot(connection, item_type, item_id)?;
+        if before != after {
+            touch(connection, item_type, &[item_id])?;
+            after = snapshot(connection, item_type, item_id)?;
+            record_history(connection, item_type, item_id, action, before, after)?;
+        }
+        Ok(result)
+    })
+}
+
+/// Sets `updated` to now on the given items
+fn touch(
+    connection: &mut DbConnection,
+    item_type: ItemType,
+    item_ids: &[i32],
+) -> Result<usize, Error> {
+    use crate::schema::{bookmarks, folders};
+    let now = time::OffsetDateTime::now_local().unwrap_or(time::OffsetDateTime::now_utc());
+    match item_type {
+        ItemType::Folder => diesel::update(folders::table.filter(folders::id.eq_any(item_ids)))
+            .set(folders::updated.eq(now))
+            .execute(connection),
+        ItemType::Bookmark => {
+            diesel::update(bookmarks::table.filter(bookmarks::id.eq_any(item_ids)))
+                .set(bookmarks::updated.eq(now))
+                .execute(connection)
+        }
+    }
+}
+
+/// Bookmarks carrying any of the given tags
+fn get_tagged_bookmark_ids(
+    connection: &mut DbConnection,
+    tag_ids: &[i32],
+) -> Result<Vec<i32>, Error> {
+    use crate::schema::bookmark_tags;
+    bookmark_tags::table
+        .filter(bookmark_tags::tag_id.eq_any(tag_ids))
+        .select(bookmark_tags::bookmark_id)
+        .distinct()
+        .load(connection)
+}
+
+/// The item as JSON, bookmarks include their tags. `None` if the item doesn't exist.
+fn snapshot(
+    connection: &mut DbConnection,
+    item_type: ItemType,
+    item_id: i32,
+) -> Result<Option<String>, Error> {
+    use crate::schema::{bookmark_tags, bookmarks, folders, tags};
+    let value = match item_type {
+        ItemType::Folder => {
+            let Some(folder) = folders::table
+                .find(item_id)
+                .first::<Folder>(connection)
+                .optional()?
+            else {
+                return Ok(None);
+            };
+            serde_json::to_string(&folder)
+        }
+        ItemType::Bookmark => {
+            let Some(bookmark) = bookmarks::table
+                .find(item_id)
+                .first::<Bookmark>(connection)
+                .optional()?
+            else {
+                return Ok(None);
+            };
+            let tag_names: Vec<String> = bookmark_tags::table
+                .inner_join(tags::table)
+                .filter(bookmark_tags::bookmark_id.eq(item_id))
+                .select(tags::name)
+                .order(tags::name.asc())
+                .load(connection)?;
+            serde_json::to_string(&BookmarkNode {
+                bookmark,
+                tags: tag_names,
+            })
+        }
+    };
+
+    value
+        .map(Some)
+        .map_err(|e| Error::SerializationError(Box::new(e)))
+}
+
+fn record_history(
+    connection: &mut DbConnection,
+    item_type: ItemType,
+    item_id: i32,
+    action: HistoryAction,
+    before_state: Option<String>,
+    after_state: Option<String>,
+) -> Result<usize, Error> {
+    use crate::schema::history;
+    diesel::insert_into(history::table)
+        .values(NewHistoryEntry {
+            item_type: item_type.as_str().to_string(),
+            item_id,
+            action: action.as_str().to_string(),
+            before_state,
+            after_state,
+            created: time::OffsetDateTime::now_local().unwrap_or(time::OffsetDateTime::now_utc()),
+        })
+        .execute(connection)
+}
+
+fn import_history_entry<T: serde::Serialize>(
+    item_type: ItemType,
+    item_id: i32,
+    item: &T,
+) -> Result<NewHistoryEntry, Error> {
+    let after_state =
+        serde_json::to_string(item).map_err(|e| Error::SerializationError(Box::new(e)))?;
+    Ok(NewHistoryEntry {
+        item_type: item_type.as_str().to_string(),
+        item_id,
+        action: HistoryAction::Import.as_str().to_string(),
+        before_state: None,
+        after_state: Some(after_state),
+        created: time::OffsetDateTime::now_local().unwrap_or(time::OffsetDateTime::now_utc()),
+    })
+}
+
+/// Every recorded change to an item, newest first
+pub fn get_history(
+    connection: &mut DbConnection,
+    history_item_type: ItemType,
+    history_item_id: i32,
+) -> Result<Vec<HistoryEntry>, Error> {
+    use crate::schema::history::dsl::*;
+    history
+        .filter(item_type.eq(history_item_type.as_str()))
+        .filter(item_id.eq(history_item_id))
+        .order(id.desc())
+        .load(connection)
+}
+
+/// Puts an item back into the state it was in right after the given history entry.
+/// Items that were purged since are recreated. The revert itself is recorded as a new entry.
+/// Entries older than the item with their id are refused with `RollbackTransaction`.
+pub fn revert_history(connection: &mut DbConnection, history_id: i32) -> Result<(), Error> {
+    use crate::schema::history;
+    connection.transaction(|connection| {
+        let entry: HistoryEntry = history::table.find(history_id).first(connection)?;
+        // A purge entry has no state after it, there is nothing to go back to
+        let state = entry.after_state.ok_or(Error::NotFound)?;
+        // Ids used to be handed out again after a purge, so an old entry can share its id
+        // with an item that was created after it
+        if item_created(connection, &entry.item_type, entry.item_id)?
+            .is_some_and(|item_created| entry.created < item_created)
+        {
+            return Err(Error::RollbackTransaction);
+        }
+
+        match entry.item_type.as_str() {
+            "folder" => {
+                let folder: Folder = serde_json::from_str(&state)
+                    .map_err(|e| Error::DeserializationError(Box::new(e)))?;
+                with_history(
+                    connection,
+                    ItemType::Folder,
+                    entry.item_id,
+                    HistoryAction::Revert,
+                    |connection| revert_folder(connection, folder),
+                )
+            }
+            "bookmark" => {
+                let bookmark: BookmarkNode = serde_json::from_str(&state)
+                    .map_err(|e| Error::DeserializationError(Box::new(e)))?;
+                with_history(
+                    connection,
+                    ItemType::Bookmark,
+                    entry.item_id,
+                    HistoryAction::Revert,
+                    |connection| revert_bookmark(connection, bookmark),
+                )
+            }
+            _ => Err(Error::NotFound),
+        }
+    })
+}
+
+fn item_created(
+    connection: &mut DbConnection,
+    item_type: &str,
+    item_id: i32,
+) -> Result<Option<time::OffsetDateTime>, Error> {
+    use crate::schema::{bookmarks, folders};
+    match item_type {
+        "folder" => folders::table
+            .find(item_id)
+            .select(folders::created)
+            .first(connection)
+            .optional(),
+        "bookmark" => bookmarks::table
+            .find(item_id)
+            .select(bookmarks::created)
+            .first(connection)
+            .optional(),
+        _ => Ok(None),
+    }
+}
+
+fn revert_folder(connection: &mut DbConnection, version: Folder) -> Result<(), Error> {
+    use crate::schema::folders::dsl::*;
+    let current: Option<Folder> = folders.find(version.id).first(connection).optional()?;
+
+    // Fall back to the top level when the old parent is gone or would create a cycle
+    let target_parent_id = match version.parent_id {
+        Some(parent) if is_live_folder(connection, parent)? => {
+            if is_subfolder(connection, version.id, parent) {
+                None
+            } else {
+                Some(parent)
+            }
+        }
+        _ => None,
+    };
+
+    let target_library_id = revert_library(connection, version.library_id, target_parent_id)?;
+
+    let Some(current) = current else {
+        let new_position = next_folder_position(connection, target_library_id, target_parent_id)?;
+        diesel::insert_into(folders)
+            .values(Folder {
+                parent_id: target_parent_id,
+                position: new_position,
+                library_id: target_library_id,
+                ..version
+            })
+            .execute(connection)?;
+        return Ok(());
+    };
+
+    match (current.deleted_at, version.deleted_at) {
+        (Some(_), None) => {
+            untrash_folder(connection, version.id)?;
+        }
+        (None, Some(_)) => {
+            trash_folder(connection, version.id)?;
+        }
+        _ => {}
+    }
+    place_folder(
+        connection,
+        version.id,
+        Some(target_library_id),
+        target_parent_id,
+        None,
+    )?;
+
+    let new_favorite_position = if version.favorite && !current.favorite {
+        next_favorite_position(connection, target_library_id)?
+    } else {
+        current.favorite_position
+    };
+    diesel::update(folders.find(version.id))
+        .set((
+            name.eq(version.name),
+            favorite.eq(version.favorite),
+            favorite_position.eq(new_favorite_position),
+        ))
+        .execute(connection)?;
+
+    Ok(())
+}
+
+fn revert_bookmark(connection: &mut DbConnection, version: BookmarkNode) -> Result<(), Error> {
+    use crate::schema::bookmarks::dsl::*;
+    let BookmarkNode {
+        bookmark: version,
+        tags: version_tags,
+    } = version;
+    let current: Option<Bookmark> = bookmarks.find(version.id).first(connection).optional()?;
+
+    let target_folder_id = match version.folder_id {
+        Some(parent) if is_live_folder(connection, parent)? => Some(parent),
+        _ => None,
+    };
+
+    let target_library_id = revert_library(connection, version.library_id, target_folder_id)?;
+
+    let Some(current) = current else {
+        let bookmark_id = version.id;
+        let new_position = next_bookmark_position(connection, target_library_id, target_folder_id)?;
+        diesel::insert_into(bookmarks)
+            .values(Bookmark {
+                folder_id: target_folder_id,
+                position: new_position,
+                library_id: target_library_id,
+                ..version
+            })
+            .execute(connection)?;
+        set_bookmark_tags(connection, bookmark_id, version_tags)?;
+        return Ok(());
+    };
+
+    match (current.deleted_at, version.deleted_at) {
+        (Some(_), None) => {
+            untrash_bookmark(connection, version.id)?;
+        }
+        (None, Some(_)) => {
+            trash_bookmark(connection, version.id)?;
+        }
+        _ => {}
+    }
+    place_bookmark(
+        connection,
+        version.id,
+        Some(target_library_id),
+        target_folder_id,
+        None,
+    )?;
+
+    let new_favorite_position = if version.favorite && !current.favorite {
+        next_favorite_position(connection, target_library_id)?
     } else {
-        bookmarks.filter(folder_id.is_null()).load(connection)
+        current.favorite_position
+    };
+    diesel::update(bookmarks.find(version.id))
+        .set((
+            name.eq(version.name),
+            url.eq(version.url),
+            favicon.eq(version.favicon),
+            favicon_url.eq(version.favicon_url),
+            description.eq(version.description),
+            favorite.eq(version.favorite),
+            favorite_position.eq(new_favorite_position),
+            read_later.eq(version.read_later),
+            read_at.eq(version.read_at),
+            keyword.eq(version.keyword),
+            queued_at.eq(version.queued_at),
+        ))
+        .execute(connection)?;
+    set_bookmark_tags(connection, version.id, version_tags)?;
+
+    Ok(())
+}
+
+/// The read-later queue, in the order items were queued. Read items are only included when asked for.
+pub fn get_read_later(
+    connection: &mut DbConnection,
+    selected_library_id: i32,
+    include_read: bool,
+) -> Result<Vec<Bookmark>, Error> {
+    use crate::schema::bookmarks::dsl::*;
+    let mut query = bookmarks
+        .filter(library_id.eq(selected_library_id))
+        .filter(read_later.eq(true))
+        .filter(deleted_at.is_null())
+        .into_boxed();
+    if !include_read {
+        query = query.filter(read_at.is_null());
+    }
+    let mut queue: Vec<Bookmark> = query.load(connection)?;
+    queue.sort_by_key(|bookmark| (bookmark.queued_at.unwrap_or(bookmark.created), bookmark.id));
+
+    Ok(queue)
+}
+
+pub fn mark_bookmark_read(connection: &mut DbConnection, bookmark_id: i32) -> Result<usize, Error> {
+    use crate::schema::bookmarks::dsl::*;
+    with_history(
+        connection,
+        ItemType::Bookmark,
+        bookmark_id,
+        HistoryAction::Update,
+        |connection| {
+            let now = time::OffsetDateTime::now_local().unwrap_or(time::OffsetDateTime::now_utc());
+            diesel::update(bookmarks.find(bookmark_id))
+                .set((read_later.eq(true), read_at.eq(now)))
+                .execute(connection)
+        },
+    )
+}
+
+/// Puts a bookmark (back) into the read-later queue
+pub fn mark_bookmark_unread(
+    connection: &mut DbConnection,
+    bookmark_id: i32,
+) -> Result<usize, Error> {
+    use crate::schema::bookmarks::dsl::*;
+    with_history(
+        connection,
+        ItemType::Bookmark,
+        bookmark_id,
+        HistoryAction::Update,
+        |connection| {
+            let now = time::OffsetDateTime::now_local().unwrap_or(time::OffsetDateTime::now_utc());
+            diesel::update(bookmarks.find(bookmark_id))
+                .set((
+                    read_later.eq(true),
+                    read_at.eq(None::<time::OffsetDateTime>),
+                    queued_at.eq(now),
+                ))
+                .execute(connection)
+        },
+    )
+}
+
+/// Counts a visit and returns the bookmark's URL, or `None` if it doesn't exist or is in the trash
+pub fn record_visit(
+    connection: &mut DbConnection,
+    bookmark_id: i32,
+) -> Result<Option<String>, Error> {
+    use crate::schema::bookmarks::dsl::*;
+    let now = time::OffsetDateTime::now_local().unwrap_or(time::OffsetDateTime::now_utc());
+    diesel::update(bookmarks.find(bookmark_id).filter(deleted_at.is_null()))
+        .set((visit_count.eq(visit_count + 1), last_visited.eq(now)))
+        .returning(url)
+        .get_result(connection)
+        .optional()
+}
+
+pub fn get_all_libraries(connection: &mut DbConnection) -> Result<Vec<Library>, Error> {
+    use crate::schema::libraries::dsl::*;
+    libraries.order(id.asc()).load(connection)
+}
+
+pub fn create_library(connection: &mut DbConnection, new_name: &str) -> Result<i32, Error> {
+    use crate::schema::libraries::{self, id};
+    diesel::insert_into(libraries::table)
+        .values(NewLibrary {
+            name: new_name.trim().to_string(),
+            created: time::OffsetDateTime::now_local().unwrap_or(time::OffsetDateTime::now_utc()),
+        })
+        .returning(id)
+        .get_result(connection)
+}
+
+pub fn rename_library(
+    connection: &mut DbConnection,
+    selected_library_id: i32,
+    new_name: &str,
+) -> Result<usize, Error> {
+    use crate::schema::libraries::dsl::*;
+    diesel::update(libraries.find(selected_library_id))
+        .set(name.eq(new_name.trim()))
+        .execute(connection)
+}
+
+/// Deletes a library and everything in it for good, trash included.
+/// The items' last states are kept in the history like a purge.
+pub fn delete_library(
+    connection: &mut DbConnection,
+    selected_library_id: i32,
+) -> Result<usize, Error> {
+    use crate::schema::{bookmarks, folders, libraries, separators};
+    connection.transaction(|connection| {
+        let folder_ids: Vec<i32> = folders::table
+            .select(folders::id)
+            .filter(folders::library_id.eq(selected_library_id))
+            .load(connection)?;
+        let bookmark_ids: Vec<i32> = bookmarks::table
+            .select(bookmarks::id)
+            .filter(bookmarks::library_id.eq(selected_library_id))
+            .load(connection)?;
+        record_purges(connection, ItemType::Folder, folder_ids.iter().copied())?;
+        record_purges(connection, ItemType::Bookmark, bookmark_ids.iter().copied())?;
+
+        diesel::delete(bookmarks::table.filter(bookmarks::id.eq_any(&bookmark_ids)))
+            .execute(connection)?;
+        diesel::delete(folders::table.filter(folders::id.eq_any(&folder_ids)))
+            .execute(connection)?;
+        diesel::delete(separators::table.filter(separators::library_id.eq(selected_library_id)))
+            .execute(connection)?;
+        diesel::delete(libraries::table.find(selected_library_id)).execute(connection)
+    })
+}
+
+pub fn library_exists(
+    connection: &mut DbConnection,
+    selected_library_id: i32,
+) -> Result<bool, Error> {
+    use crate::schema::libraries::dsl::*;
+    diesel::select(diesel::dsl::exists(libraries.find(selected_library_id))).get_result(connection)
+}
+
+/// The library an item placed in `parent_folder_id` belongs to: the folder's own library,
+/// or `requested_library_id` at the top level. Fails with `NotFound` for unknown libraries.
+fn resolve_library(
+    connection: &mut DbConnection,
+    requested_library_id: i32,
+    parent_folder_id: Option<i32>,
+) -> Result<i32, Error> {
+    use crate::schema::{folders, libraries};
+    match parent_folder_id {
+        Some(parent) => folders::table
+            .select(folders::library_id)
+            .find(parent)
+            .first(connection),
+        None => libraries::table
+            .select(libraries::id)
+            .find(requested_library_id)
+            .first(connection),
+    }
+}
+
+/// Like `resolve_library`, but falls back to the default library when the old one is gone
+fn revert_library(
+    connection: &mut DbConnection,
+    version_library_id: i32,
+    parent_folder_id: Option<i32>,
+) -> Result<i32, Error> {
+    match resolve_library(connection, version_library_id, parent_folder_id) {
+        Err(Error::NotFound) => Ok(DEFAULT_LIBRARY_ID),
+        result => result,
+    }
+}
+
+/// Hands a folder's whole subtree, trashed items included, over to another library
+fn move_folder_to_library(
+    connection: &mut DbConnection,
+    folder_id: i32,
+    new_library_id: i32,
+) -> Result<(), Error> {
+    use crate::schema::{bookmarks, folders, separators};
+    let mut folder_ids = get_descendant_folder_ids(connection, folder_id)?;
+    folder_ids.push(folder_id);
+
+    diesel::update(folders::table.filter(folders::id.eq_any(&folder_ids)))
+        .set(folders::library_id.eq(new_library_id))
+        .execute(connection)?;
+    diesel::update(bookmarks::table.filter(bookmarks::folder_id.eq_any(&folder_ids)))
+        .set(bookmarks::library_id.eq(new_library_id))
+        .execute(connection)?;
+    diesel::update(separators::table.filter(separators::folder_id.eq_any(&folder_ids)))
+        .set(separators::library_id.eq(new_library_id))
+        .execute(connection)?;
+
+    Ok(())
+}
+
+/// A fresh database in a temporary file with all migrations applied
+#[cfg(test)]
+pub fn test_pool() -> Pool {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    static NEXT_DATABASE: AtomicUsize = AtomicUsize::new(0);
+    let path = std::env::temp_dir().join(format!(
+        "nadamark-test-{}-{}.db",
+        std::process::id(),
+        NEXT_DATABASE.fetch_add(1, Ordering::Relaxed)
+    ));
+    let _ = fs::remove_file(&path);
+    let manager = ConnectionManager::<SqliteConnection>::new(path.to_string_lossy());
+    let pool = r2d2::Pool::builder()
+        .max_size(1)
+        .connection_customizer(Box::new(ConnectionOptions))
+        .build(manager)
+        .expect("Failed to create pool");
+    pool.get()
+        .expect("Failed to get connection from pool")
+        .run_pending_migrations(MIGRATIONS)
+        .expect("Failed to initialize database");
+    pool
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_bookmark(name: &str, url: &str) -> NewBookmarkRequest {
+        NewBookmarkRequest {
+            library_id: DEFAULT_LIBRARY_ID,
+            name: name.to_string(),
+            url: url.to_string(),
+            folder_id: None,
+            tags: Vec::new(),
+            description: None,
+            read_later: false,
+        }
+    }
+
+    fn get_bookmark(connection: &mut DbConnection, bookmark_id: i32) -> Bookmark {
+        use crate::schema::bookmarks::dsl::*;
+        bookmarks.find(bookmark_id).first(connection).unwrap()
+    }
+
+    #[test]
+    fn read_later_queue_is_in_queued_order() {
+        let pool = test_pool();
+        let mut connection = pool.get().unwrap();
+        let older_id = create_new_bookmark(
+            &mut connection,
+            NewBookmarkRequest {
+                read_later: true,
+                ..new_bookmark("Older", "https://older.com")
+            },
+        )
+        .unwrap();
+        let newer_id =
+            create_new_bookmark(&mut connection, new_bookmark("Newer", "https://newer.com"))
+                .unwrap();
+        assert!(get_bookmark(&mut connection, newer_id).queued_at.is_none());
+
+        // Queued after the older bookmark, even though it was created later
+        mark_bookmark_unread(&mut connection, newer_id).unwrap();
+        mark_bookmark_read(&mut connection, older_id).unwrap();
+        // Putting a read item back moves it to the end of the queue
+        mark_bookmark_unread(&mut connection, older_id).unwrap();
+
+        let queue: Vec<i32> = get_read_later(&mut connection, DEFAULT_LIBRARY_ID, false)
+            .unwrap()
+            .iter()
+            .map(|bookmark| bookmark.id)
+            .collect();
+        assert_eq!(queue, vec![newer_id, older_id]);
+    }
+
+    #[test]
+    fn reordering_favorites_is_recorded_and_checked() {
+        let pool = test_pool();
+        let mut connection = pool.get().unwrap();
+        let first_id =
+            create_new_bookmark(&mut connection, new_bookmark("First", "https://first.com"))
+                .unwrap();
+        let second_id = create_new_bookmark(
+            &mut connection,
+            new_bookmark("Second", "https://second.com"),
+        )
+        .unwrap();
+        let plain_id =
+            create_new_bookmark(&mut connection, new_bookmark("Plain", "https://plain.com"))
+                .unwrap();
+        toggle_bookmark_favorite(&mut connection, first_id).unwrap();
+        toggle_bookmark_favorite(&mut connection, second_id).unwrap();
+        let favorite = |item_id| FavoriteRef {
+            item_type: ItemType::Bookmark,
+            item_id,
+        };
+
+        reorder_favorites(
+            &mut connection,
+            DEFAULT_LIBRARY_ID,
+            vec![favorite(second_id), favorite(first_id)],
+        )
+        .unwrap();
+        assert_eq!(
+            get_bookmark(&mut connection, second_id).favorite_position,
+            0
+        );
+        assert_eq!(get_bookmark(&mut connection, first_id).favorite_position, 1);
+        for bookmark_id in [first_id, second_id] {
+            let history = get_history(&mut connection, ItemType::Bookmark, bookmark_id).unwrap();
+            // Made a favorite, then moved on the bar
+            assert_eq!(
+                history
+                    .iter()
+                    .filter(|entry| entry.action == "favorite")
+                    .count(),
+                2
+            );
+        }
+
+        // An item that isn't a favorite, or is in another library, stops the whole reorder
+        let other_library_id = create_library(&mut connection, "Work").unwrap();
+        for library_id in [DEFAULT_LIBRARY_ID, other_library_id] {
+            let favorites = vec![favorite(first_id), favorite(plain_id)];
+            assert!(matches!(
+                reorder_favorites(&mut connection, library_id, favorites),
+                Err(Error::NotFound)
+            ));
+        }
+        assert_eq!(get_bookmark(&mut connection, first_id).favorite_position, 1);
+        assert!(!get_bookmark(&mut connection, plain_id).favorite);
+    }
+
+    #[test]
+    fn purged_ids_are_not_reused() {
+        let pool = test_pool();
+        let mut connection = pool.get().unwrap();
+        let old_id =
+            create_new_bookmark(&mut connection, new_bookmark("Old", "https://old.com")).unwrap();
+        delete_bookmark(&mut connection, old_id).unwrap();
+        purge_trash(&mut connection, None, None).unwrap();
+
+        let new_id =
+            create_new_bookmark(&mut connection, new_bookmark("New", "https://new.com")).unwrap();
+        assert_ne!(new_id, old_id);
+
+        // Reverting the purged bookmark brings it back without touching the new one
+        let old_history = get_history(&mut connection, ItemType::Bookmark, old_id).unwrap();
+        let created_entry = old_history.last().unwrap();
+        revert_history(&mut connection, created_entry.id).unwrap();
+        assert_eq!(get_bookmark(&mut connection, old_id).url, "https://old.com");
+        let new_bookmark = get_bookmark(&mut connection, new_id);
+        assert_eq!(new_bookmark.name, "New");
+        assert_eq!(new_bookmark.url, "https://new.com");
+    }
+
+    #[test]
+    fn revert_refuses_entries_older_than_the_item() {
+        use crate::schema::history;
+        let pool = test_pool();
+        let mut connection = pool.get().unwrap();
+        let bookmark_id =
+            create_new_bookmark(&mut connection, new_bookmark("New", "https://new.com")).unwrap();
+
+        // What a purged bookmark with the same id left behind before ids stopped being reused
+        let mut old_bookmark = get_bookmark(&mut connection, bookmark_id);
+        old_bookmark.name = "Old".to_string();
+        old_bookmark.url = "https://old.com".to_string();
+        let old_entry_id: i32 = diesel::insert_into(history::table)
+            .values(NewHistoryEntry {
+                item_type: ItemType::Bookmark.as_str().to_string(),
+                item_id: bookmark_id,
+                action: HistoryAction::Create.as_str().to_string(),
+                before_state: None,
+                after_state: Some(
+                    serde_json::to_string(&BookmarkNode {
+                        bookmark: old_bookmark.clone(),
+                        tags: Vec::new(),
+                    })
+                    .unwrap(),
+                ),
+                created: old_bookmark.created - time::Duration::days(1),
+            })
+            .returning(history::id)
+            .get_result(&mut connection)
+            .unwrap();
+
+        assert!(matches!(
+            revert_history(&mut connection, old_entry_id),
+            Err(Error::RollbackTransaction)
+        ));
+        let bookmark = get_bookmark(&mut connection, bookmark_id);
+        assert_eq!(bookmark.name, "New");
+        assert_eq!(bookmark.url, "https://new.com");
+    }
+
+    #[test]
+    fn trash_tags_and_favorites_stay_in_their_library() {
+        let pool = test_pool();
+        let mut connection = pool.get().unwrap();
+        let other_library_id = create_library(&mut connection, "Work").unwrap();
+
+        let mut ids = Vec::new();
+        for library_id in [DEFAULT_LIBRARY_ID, other_library_id] {
+            let kept = create_new_bookmark(
+                &mut connection,
+                NewBookmarkRequest {
+                    library_id,
+                    tags: vec![format!("only-{}", library_id), "shared".to_string()],
+                    ..new_bookmark("Kept", "https://kept.com")
+                },
+            )
+            .unwrap();
+            let trashed = create_new_bookmark(
+                &mut connection,
+                NewBookmarkRequest {
+                    library_id,
+                    ..new_bookmark("Trashed", "https://trashed.com")
+                },
+            )
+            .unwrap();
+            delete_bookmark(&mut connection, trashed).unwrap();
+            ids.push((kept, trashed));
+        }
+
+        let trash = get_trash(&mut connection, other_library_id).unwrap();
+        assert!(trash.folders.is_empty());
+        assert_eq!(
+            trash
+                .bookmarks
+                .iter()
+                .map(|bookmark| bookmark.id)
+                .collect::<Vec<_>>(),
+            vec![ids[1].1]
+        );
+
+        let tags = get_all_tags(&mut connection, other_library_id).unwrap();
+        assert_eq!(
+            tags.iter()
+                .map(|tag| (tag.name.as_str(), tag.bookmark_count))
+                .collect::<Vec<_>>(),
+            vec![
+                (format!("only-{}", other_library_id).as_str(), 1),
+                ("shared", 1)
+            ]
+        );
+
+        // Each library's favorites bar is numbered on its own
+        toggle_bookmark_favorite(&mut connection, ids[0].0).unwrap();
+        toggle_bookmark_favorite(&mut connection, ids[1].0).unwrap();
+        assert_eq!(get_bookmark(&mut connection, ids[0].0).favorite_position, 0);
+        assert_eq!(get_bookmark(&mut connection, ids[1].0).favorite_position, 0);
+        assert_eq!(
+            next_favorite_position(&mut connection, other_library_id).unwrap(),
+            1
+        );
+
+        // Emptying one library's trash leaves the other's alone
+        assert_eq!(
+            purge_trash(&mut connection, Some(other_library_id), None).unwrap(),
+            1
+        );
+        assert_eq!(get_bookmark(&mut connection, ids[0].1).name, "Trashed");
+        assert!(get_trash(&mut connection, other_library_id)
+            .unwrap()
+            .bookmarks
+            .is_empty());
     }
 }
@@ -1,6 +1,7 @@
 use std::sync::Arc;
 
 use axum::{extract::State, http::StatusCode, Json};
+use diesel::result::Error;
 
 use crate::{
     database::{self, is_subfolder, Pool},
@@ -24,22 +25,34 @@ pub async fn handle_move(
             }
         }
 
-        if let Err(e) = database::change_folder_parent(
+        match database::change_folder_parent(
             &mut connection,
             payload.item_id,
+            payload.target_library_id,
             payload.target_folder_id,
+            payload.target_index,
         ) {
-            eprintln!("Failed to change folder's parent id: {}", e);
-            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+            Ok(_) => {}
+            Err(Error::NotFound) => return Err(StatusCode::NOT_FOUND),
+            Err(e) => {
+                eprintln!("Failed to change folder's parent id: {}", e);
+                return Err(StatusCode::INTERNAL_SERVER_ERROR);
+            }
         }
     } else if payload.item_type == ItemType::Bookmark {
-        if let Err(e) = database::change_bookmark_folder(
+        match database::change_bookmark_folder(
             &mut connection,
             payload.item_id,
+            payload.target_library_id,
             payload.target_folder_id,
+            payload.target_index,
         ) {
-            eprintln!("Failed to change bookmark's folder id: {}", e);
-            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+            Ok(_) => {}
+            Err(Error::NotFound) => return Err(StatusCode::NOT_FOUND),
+            Err(e) => {
+                eprintln!("Failed to change bookmark's folder id: {}", e);
+                return Err(StatusCode::INTERNAL_SERVER_ERROR);
+            }
         }
     } else {
         return Err(StatusCode::BAD_REQUEST);
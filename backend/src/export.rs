@@ -1,45 +1,44 @@
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
 use axum::{
-    extract::State,
+    extract::{Query, State},
+    http::header,
     response::{Html, IntoResponse},
 };
+use quick_xml::escape::escape;
+use time::{
+    format_description::well_known::{Rfc2822, Rfc3339},
+    OffsetDateTime,
+};
 
-use crate::database::{self, get_all_bookmarks, get_all_folders, DbConnection, Pool};
+use crate::{
+    database::{self, get_all_bookmarks, get_all_folders, DbConnection, Pool},
+    models::{Bookmark, Folder, LibraryQuery},
+};
 
-pub async fn export_bookmarks(State(pool): State<Arc<Pool>>) -> impl IntoResponse {
+pub async fn export_bookmarks(
+    State(pool): State<Arc<Pool>>,
+    Query(query): Query<LibraryQuery>,
+) -> impl IntoResponse {
     let mut connection = pool.get().expect("Failed to get connection from pool");
-    if !get_all_folders(&mut connection)
+    if !get_all_folders(&mut connection, query.library_id)
         .unwrap_or(vec![])
         .is_empty()
-        || !get_all_bookmarks(&mut connection)
+        || !get_all_bookmarks(&mut connection, query.library_id)
             .unwrap_or(vec![])
             .is_empty()
     {
-        let html = traverse_bookmarks(&mut connection, None, &mut String::new(), 0);
-        return Html(html).into_response();
+        return Html(bookmarks_html(&mut connection, query.library_id)).into_response();
     }
 
     Html("").into_response()
 }
 
-fn traverse_bookmarks(
-    mut connection: &mut DbConnection,
-    parent_folder_id: Option<i32>,
-    html: &mut String,
-    tabs: usize,
-) -> String {
-    let child_folders =
-        database::get_all_child_folders(&mut connection, &parent_folder_id).unwrap();
-    let child_bookmarks =
-        database::get_all_child_bookmarks(&mut connection, &parent_folder_id).unwrap_or(vec![]);
-    let add_tags = !child_folders.is_empty() || !child_bookmarks.is_empty();
-    let mut further_tabs = tabs;
-
-    if add_tags {
-        if parent_folder_id.is_none() {
-            html.push_str(
-                r#"
+/// The library as a Netscape bookmark file
+pub fn bookmarks_html(connection: &mut DbConnection, library_id: i32) -> String {
+    let bookmark_tags = database::get_all_bookmark_tags(connection).unwrap_or_default();
+    let mut html = String::from(
+        r#"
 <!DOCTYPE NETSCAPE-Bookmark-file-1>
 <!-- This is an automatically generated file.
     It will be read and overwritten.
@@ -48,53 +47,427 @@ fn traverse_bookmarks(
 <TITLE>Bookmarks</TITLE>
 <H1>Bookmarks</H1>
 
+<DL><p>
 "#,
+    );
+
+    // Browsers have a single toolbar folder, it holds the favorites bar in its order
+    let mut favorite_folders = Vec::new();
+    let mut favorite_bookmarks = Vec::new();
+    collect_favorites(
+        connection,
+        library_id,
+        None,
+        &mut favorite_folders,
+        &mut favorite_bookmarks,
+    );
+    if !favorite_folders.is_empty() || !favorite_bookmarks.is_empty() {
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+        html.push_str(&format!(
+            "{}<DT><H3 ADD_DATE=\"{}\" LAST_MODIFIED=\"{}\" PERSONAL_TOOLBAR_FOLDER=\"true\">{}</H3>\n",
+            tab_chars(1),
+            now,
+            now,
+            TOOLBAR_FOLDER_NAME
+        ));
+        html.push_str(&format!("{}<DL><p>\n", tab_chars(1)));
+        let mut favorites: Vec<(i32, Option<Folder>, Option<Bookmark>)> = favorite_folders
+            .into_iter()
+            .map(|folder| (folder.favorite_position, Some(folder), None))
+            .chain(
+                favorite_bookmarks
+                    .into_iter()
+                    .map(|bookmark| (bookmark.favorite_position, None, Some(bookmark))),
+            )
+            .collect();
+        // Folders come before bookmarks when their positions are equal, like on the bar
+        favorites
+            .sort_by_key(|(favorite_position, folder, _)| (*favorite_position, folder.is_none()));
+        for (_, folder, bookmark) in favorites {
+            if let Some(folder) = folder {
+                write_folder(
+                    connection,
+                    library_id,
+                    folder,
+                    &bookmark_tags,
+                    &mut html,
+                    2,
+                    false,
+                );
+            }
+            if let Some(bookmark) = bookmark {
+                write_bookmark(bookmark, &bookmark_tags, &mut html, 2);
+            }
+        }
+        html.push_str(&format!("{}</DL><p>\n", tab_chars(1)));
+    }
+
+    traverse_bookmarks(
+        connection,
+        library_id,
+        None,
+        &bookmark_tags,
+        &mut html,
+        1,
+        true,
+    );
+    html.push_str("</DL>\n");
+    html
+}
+
+/// The name of the toolbar folder, as browsers call it
+const TOOLBAR_FOLDER_NAME: &str = "Bookmarks Toolbar";
+
+/// Favorite folders and bookmarks that aren't already inside a favorite folder
+fn collect_favorites(
+    connection: &mut DbConnection,
+    library_id: i32,
+    parent_folder_id: Option<i32>,
+    favorite_folders: &mut Vec<Folder>,
+    favorite_bookmarks: &mut Vec<Bookmark>,
+) {
+    for bookmark in database::get_all_child_bookmarks(connection, library_id, &parent_folder_id)
+        .unwrap_or_default()
+    {
+        if bookmark.favorite {
+            favorite_bookmarks.push(bookmark);
+        }
+    }
+    for folder in database::get_all_child_folders(connection, library_id, &parent_folder_id)
+        .unwrap_or_default()
+    {
+        if folder.favorite {
+            favorite_folders.push(folder);
+        } else {
+            collect_favorites(
+                connection,
+                library_id,
+                Some(folder.id),
+                favorite_folders,
+                favorite_bookmarks,
             );
         }
-        html.push_str(&format!("{}<DL><p>\n", tab_chars(tabs)));
-        further_tabs += 1;
     }
+}
+
+/// Favorites are left out when `skip_favorites` is set, because they are in the toolbar folder
+fn traverse_bookmarks(
+    connection: &mut DbConnection,
+    library_id: i32,
+    parent_folder_id: Option<i32>,
+    bookmark_tags: &HashMap<i32, Vec<String>>,
+    html: &mut String,
+    tabs: usize,
+    skip_favorites: bool,
+) {
+    let child_folders =
+        database::get_all_child_folders(connection, library_id, &parent_folder_id).unwrap();
+    let child_bookmarks =
+        database::get_all_child_bookmarks(connection, library_id, &parent_folder_id)
+            .unwrap_or(vec![]);
 
     for folder in child_folders {
-        let add_date = folder.created.unix_timestamp();
+        if skip_favorites && folder.favorite {
+            continue;
+        }
+        write_folder(
+            connection,
+            library_id,
+            folder,
+            bookmark_tags,
+            html,
+            tabs,
+            skip_favorites,
+        );
+    }
+
+    for bookmark in child_bookmarks {
+        if skip_favorites && bookmark.favorite {
+            continue;
+        }
+        write_bookmark(bookmark, bookmark_tags, html, tabs);
+    }
+}
+
+fn write_folder(
+    connection: &mut DbConnection,
+    library_id: i32,
+    folder: Folder,
+    bookmark_tags: &HashMap<i32, Vec<String>>,
+    html: &mut String,
+    tabs: usize,
+    skip_favorites: bool,
+) {
+    html.push_str(&format!(
+        "{}<DT><H3 ADD_DATE=\"{}\" LAST_MODIFIED=\"{}\">{}</H3>\n",
+        tab_chars(tabs),
+        folder.created.unix_timestamp(),
+        folder.updated.unix_timestamp(),
+        escape(&folder.name)
+    ));
+
+    let mut children = String::new();
+    traverse_bookmarks(
+        connection,
+        library_id,
+        Some(folder.id),
+        bookmark_tags,
+        &mut children,
+        tabs + 1,
+        skip_favorites,
+    );
+    if !children.is_empty() {
+        html.push_str(&format!("{}<DL><p>\n", tab_chars(tabs)));
+        html.push_str(&children);
+        html.push_str(&format!("{}</DL><p>\n", tab_chars(tabs)));
+    }
+}
+
+fn write_bookmark(
+    bookmark: Bookmark,
+    bookmark_tags: &HashMap<i32, Vec<String>>,
+    html: &mut String,
+    tabs: usize,
+) {
+    let icon = match bookmark.favicon {
+        Some(url) => &format!(" ICON=\"{}\"", escape(&url)),
+        None => "",
+    };
+    let icon_uri = match bookmark.favicon_url {
+        Some(url) => &format!(" ICON_URI=\"{}\"", escape(&url)),
+        None => "",
+    };
+    let keyword = match bookmark.keyword {
+        Some(keyword) => &format!(" SHORTCUTURL=\"{}\"", escape(&keyword)),
+        None => "",
+    };
+    let to_read = if bookmark.read_later && bookmark.read_at.is_none() {
+        " TOREAD=\"1\""
+    } else {
+        ""
+    };
+    let tags = match bookmark_tags.get(&bookmark.id) {
+        Some(tags) if !tags.is_empty() => &format!(" TAGS=\"{}\"", escape(tags.join(","))),
+        _ => "",
+    };
+
+    html.push_str(&format!(
+        "{}<DT><A HREF=\"{}\" ADD_DATE=\"{}\" LAST_MODIFIED=\"{}\"{}{}{}{}{}>{}</A>\n",
+        tab_chars(tabs),
+        escape(&bookmark.url),
+        bookmark.created.unix_timestamp(),
+        bookmark.updated.unix_timestamp(),
+        icon,
+        icon_uri,
+        keyword,
+        tags,
+        to_read,
+        escape(&bookmark.name)
+    ));
+    if let Some(description) = bookmark.description {
         html.push_str(&format!(
-            "{}<DT><H3 ADD_DATE=\"{}\">{}</H3>\n",
-            tab_chars(further_tabs),
-            add_date,
-            folder.name
+            "{}<DD>{}\n",
+            tab_chars(tabs),
+            escape(&description)
         ));
-        traverse_bookmarks(&mut connection, Some(folder.id), html, further_tabs);
+    }
+}
+
+pub async fn export_bookmarks_xbel(
+    State(pool): State<Arc<Pool>>,
+    Query(query): Query<LibraryQuery>,
+) -> impl IntoResponse {
+    let mut connection = pool.get().expect("Failed to get connection from pool");
+    (
+        [(header::CONTENT_TYPE, "application/xml; charset=utf-8")],
+        bookmarks_xbel(&mut connection, query.library_id),
+    )
+}
+
+/// The library as an XBEL document
+pub fn bookmarks_xbel(connection: &mut DbConnection, library_id: i32) -> String {
+    let mut xbel = String::from(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE xbel PUBLIC "+//IDN python.org//DTD XML Bookmark Exchange Language 1.0//EN//XML" "http://pyxml.sourceforge.net/topics/dtds/xbel.dtd">
+<xbel version="1.0">
+"#,
+    );
+    traverse_xbel(connection, library_id, None, &mut xbel, 1);
+    xbel.push_str("</xbel>\n");
+    xbel
+}
+
+/// Folders come first like in the tree, separators go before the bookmark in their position
+fn traverse_xbel(
+    connection: &mut DbConnection,
+    library_id: i32,
+    parent_folder_id: Option<i32>,
+    xbel: &mut String,
+    tabs: usize,
+) {
+    let child_folders = database::get_all_child_folders(connection, library_id, &parent_folder_id)
+        .unwrap_or_default();
+    let child_bookmarks =
+        database::get_all_child_bookmarks(connection, library_id, &parent_folder_id)
+            .unwrap_or_default();
+    let mut child_separators =
+        database::get_all_child_separators(connection, library_id, &parent_folder_id)
+            .unwrap_or_default()
+            .into_iter()
+            .peekable();
+
+    for folder in child_folders {
+        xbel.push_str(&format!(
+            "{}<folder added=\"{}\" modified=\"{}\">\n{}<title>{}</title>\n",
+            tab_chars(tabs),
+            xbel_date(folder.created),
+            xbel_date(folder.updated),
+            tab_chars(tabs + 1),
+            escape(&folder.name)
+        ));
+        traverse_xbel(connection, library_id, Some(folder.id), xbel, tabs + 1);
+        xbel.push_str(&format!("{}</folder>\n", tab_chars(tabs)));
     }
 
     for bookmark in child_bookmarks {
-        let add_date = bookmark.created.unix_timestamp();
-        let icon = match bookmark.favicon {
-            Some(url) => &format!(" ICON=\"{}\"", url),
+        while child_separators
+            .next_if(|separator| separator.position <= bookmark.position)
+            .is_some()
+        {
+            xbel.push_str(&format!("{}<separator/>\n", tab_chars(tabs)));
+        }
+        xbel.push_str(&format!(
+            "{}<bookmark href=\"{}\" added=\"{}\" modified=\"{}\">\n{}<title>{}</title>\n",
+            tab_chars(tabs),
+            escape(&bookmark.url),
+            xbel_date(bookmark.created),
+            xbel_date(bookmark.updated),
+            tab_chars(tabs + 1),
+            escape(&bookmark.name)
+        ));
+        if let Some(description) = bookmark.description {
+            xbel.push_str(&format!(
+                "{}<desc>{}</desc>\n",
+                tab_chars(tabs + 1),
+                escape(&description)
+            ));
+        }
+        xbel.push_str(&format!("{}</bookmark>\n", tab_chars(tabs)));
+    }
+
+    for _ in child_separators {
+        xbel.push_str(&format!("{}<separator/>\n", tab_chars(tabs)));
+    }
+}
+
+fn xbel_date(date: OffsetDateTime) -> String {
+    date.format(&Rfc3339).unwrap_or_default()
+}
+
+pub async fn export_bookmarks_opml(
+    State(pool): State<Arc<Pool>>,
+    Query(query): Query<LibraryQuery>,
+) -> impl IntoResponse {
+    let mut connection = pool.get().expect("Failed to get connection from pool");
+    let library_name = database::get_all_libraries(&mut connection)
+        .unwrap_or_default()
+        .into_iter()
+        .find(|library| library.id == query.library_id)
+        .map_or("Bookmarks".to_string(), |library| library.name);
+    let bookmark_tags = database::get_all_bookmark_tags(&mut connection).unwrap_or_default();
+
+    let mut opml = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<opml version=\"2.0\">\n\t<head>\n\t\t<title>{}</title>\n\t\t<dateCreated>{}</dateCreated>\n\t</head>\n\t<body>\n",
+        escape(&library_name),
+        opml_date(OffsetDateTime::now_utc())
+    );
+    traverse_opml(
+        &mut connection,
+        query.library_id,
+        None,
+        &bookmark_tags,
+        &mut opml,
+        2,
+    );
+    opml.push_str("\t</body>\n</opml>\n");
+
+    ([(header::CONTENT_TYPE, "text/x-opml; charset=utf-8")], opml)
+}
+
+fn traverse_opml(
+    connection: &mut DbConnection,
+    library_id: i32,
+    parent_folder_id: Option<i32>,
+    bookmark_tags: &HashMap<i32, Vec<String>>,
+    opml: &mut String,
+    tabs: usize,
+) {
+    let child_folders = database::get_all_child_folders(connection, library_id, &parent_folder_id)
+        .unwrap_or_default();
+    let child_bookmarks =
+        database::get_all_child_bookmarks(connection, library_id, &parent_folder_id)
+            .unwrap_or_default();
+
+    for folder in child_folders {
+        let has_children =
+            !database::get_all_child_folders(connection, library_id, &Some(folder.id))
+                .unwrap_or_default()
+                .is_empty()
+                || !database::get_all_child_bookmarks(connection, library_id, &Some(folder.id))
+                    .unwrap_or_default()
+                    .is_empty();
+        opml.push_str(&format!(
+            "{}<outline text=\"{}\" created=\"{}\"",
+            tab_chars(tabs),
+            opml_attribute(&folder.name),
+            opml_date(folder.created)
+        ));
+        if has_children {
+            opml.push_str(">\n");
+            traverse_opml(
+                connection,
+                library_id,
+                Some(folder.id),
+                bookmark_tags,
+                opml,
+                tabs + 1,
+            );
+            opml.push_str(&format!("{}</outline>\n", tab_chars(tabs)));
+        } else {
+            opml.push_str("/>\n");
+        }
+    }
+
+    for bookmark in child_bookmarks {
+        let description = match bookmark.description {
+            Some(description) => &format!(" description=\"{}\"", opml_attribute(&description)),
             None => "",
         };
-        let icon_uri = match bookmark.favicon_url {
-            Some(url) => &format!(" ICON_URI=\"{}\"", url),
-            None => "",
+        let category = match bookmark_tags.get(&bookmark.id) {
+            Some(tags) if !tags.is_empty() => {
+                &format!(" category=\"{}\"", opml_attribute(&tags.join(",")))
+            }
+            _ => "",
         };
-
-        html.push_str(&format!(
-            "{}<DT><A HREF=\"{}\" ADD_DATE=\"{}\"{}{}>{}</A>\n",
-            tab_chars(further_tabs),
-            bookmark.url,
-            add_date,
-            icon,
-            icon_uri,
-            bookmark.name
+        opml.push_str(&format!(
+            "{}<outline type=\"link\" text=\"{}\" url=\"{}\" created=\"{}\"{}{}/>\n",
+            tab_chars(tabs),
+            opml_attribute(&bookmark.name),
+            opml_attribute(&bookmark.url),
+            opml_date(bookmark.created),
+            description,
+            category
         ));
     }
+}
 
-    if parent_folder_id.is_none() {
-        html.push_str("</DL>\n");
-    } else if add_tags {
-        html.push_str(&format!("{}</DL><p>\n", tab_chars(tabs)));
-    }
+/// Line breaks would be normalized to spaces inside an attribute unless they're escaped
+fn opml_attribute(value: &str) -> String {
+    escape(value).replace('\n', "&#10;")
+}
 
-    html.clone()
+fn opml_date(date: OffsetDateTime) -> String {
+    date.format(&Rfc2822).unwrap_or_default()
 }
 
 fn tab_chars(tabs: usize) -> String {
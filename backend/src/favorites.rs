@@ -0,0 +1,77 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+
+use diesel::result::Error;
+
+use crate::{
+    database::{self, Pool},
+    models::{FavoriteItem, FavoriteRef, FolderNode, LibraryQuery, SortOrder},
+    tree,
+};
+
+pub async fn list_favorites(
+    State(pool): State<Arc<Pool>>,
+    Query(query): Query<LibraryQuery>,
+) -> impl IntoResponse {
+    let mut connection = pool.get().expect("Failed to get connection from pool");
+    let root_items = tree::build_tree(&mut connection, query.library_id, SortOrder::Manual);
+
+    let mut favorites: Vec<FavoriteItem> = Vec::new();
+    for bookmark in root_items.root_bookmarks {
+        if bookmark.bookmark.favorite {
+            favorites.push(FavoriteItem::Bookmark(bookmark));
+        }
+    }
+    for folder in root_items.root_folders {
+        collect_favorites(folder, &mut favorites);
+    }
+
+    // Folders come before bookmarks when their positions are equal, like in the tree
+    favorites.sort_by_key(|item| {
+        (
+            item.favorite_position(),
+            matches!(item, FavoriteItem::Bookmark(_)),
+        )
+    });
+
+    Json(favorites)
+}
+
+/// Favorite folders are returned with their whole subtree so the bar can open them, favorites
+/// inside them aren't listed again
+fn collect_favorites(folder: FolderNode, favorites: &mut Vec<FavoriteItem>) {
+    if folder.folder.favorite {
+        favorites.push(FavoriteItem::Folder(folder));
+        return;
+    }
+    for bookmark in folder.bookmarks {
+        if bookmark.bookmark.favorite {
+            favorites.push(FavoriteItem::Bookmark(bookmark));
+        }
+    }
+    for child in folder.children {
+        collect_favorites(child, favorites);
+    }
+}
+
+pub async fn reorder_favorites(
+    State(pool): State<Arc<Pool>>,
+    Query(query): Query<LibraryQuery>,
+    Json(payload): Json<Vec<FavoriteRef>>,
+) -> impl IntoResponse {
+    let mut connection = pool.get().expect("Failed to get connection from pool");
+    match database::reorder_favorites(&mut connection, query.library_id, payload) {
+        Ok(()) => Ok(StatusCode::OK),
+        Err(Error::NotFound) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            eprintln!("Failed to reorder favorites: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
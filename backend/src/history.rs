@@ -0,0 +1,45 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use diesel::result::Error;
+
+use crate::{
+    database::{self, Pool},
+    models::ItemType,
+};
+
+pub async fn item_history(
+    State(pool): State<Arc<Pool>>,
+    Path((item_type, item_id)): Path<(ItemType, i32)>,
+) -> impl IntoResponse {
+    let mut connection = pool.get().expect("Failed to get connection from pool");
+    match database::get_history(&mut connection, item_type, item_id) {
+        Ok(entries) => Ok(Json(entries)),
+        Err(e) => {
+            eprintln!("Failed to fetch history: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+pub async fn revert_history(
+    State(pool): State<Arc<Pool>>,
+    Json(payload): Json<i32>,
+) -> impl IntoResponse {
+    let mut connection = pool.get().expect("Failed to get connection from pool");
+    match database::revert_history(&mut connection, payload) {
+        Ok(_) => Ok(StatusCode::OK),
+        Err(Error::NotFound) => Err(StatusCode::NOT_FOUND),
+        // The entry belongs to an earlier item that had the same id
+        Err(Error::RollbackTransaction) => Err(StatusCode::CONFLICT),
+        Err(e) => {
+            eprintln!("Failed to revert history: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
@@ -1,191 +1,942 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    io::Cursor,
+    path::Path,
+    sync::Arc,
+    time::SystemTime,
+};
 
 use axum::{
-    extract::State,
+    body::Bytes,
+    extract::{Query, State},
     http::StatusCode,
     response::{IntoResponse, Response},
+    Json,
+};
+use csv::ReaderBuilder;
+use diesel::{
+    sql_query,
+    sql_types::{BigInt, Integer, Nullable, Text},
+    Connection, QueryableByName, RunQueryDsl, SqliteConnection,
 };
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
 use scraper::{ElementRef, Html, Selector};
-use serde_json::{self, Value};
-use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+use serde::Deserialize;
+use serde_json::{self, json, Value};
+use time::{
+    format_description::well_known::{Rfc2822, Rfc3339},
+    OffsetDateTime,
+};
 
 use crate::{
     database::{self, DbConnection, Pool},
-    models::{Bookmark, Folder},
+    models::{
+        Bookmark, BookmarkBar, ChromiumImportQuery, FirefoxImportQuery, FirefoxRoots, Folder,
+        ImportCounts, ImportMode, ImportQuery, ImportReport, ImportedItem, NewSeparator,
+        PinboardImportQuery, ReadingList, SafariImportQuery, TreeStats,
+    },
 };
 
 pub async fn import_bookmarks_html(
     State(pool): State<Arc<Pool>>,
+    Query(query): Query<ImportQuery>,
     bookmarks_html: String,
 ) -> Response {
     let mut connection = pool.get().expect("Failed to get connection from pool");
+    if !database::library_exists(&mut connection, query.library_id).unwrap_or(false) {
+        return StatusCode::NOT_FOUND.into_response();
+    }
 
-    match parse_bookmarks_html(&mut connection, &bookmarks_html) {
-        Ok((folders_to_import, bookmarks_to_import)) => {
-            if let Err(e) = database::insert_folders(&mut connection, folders_to_import) {
-                eprintln!("Failed to import folders: {}", e);
-            };
-            if let Err(e) = database::insert_bookmarks(&mut connection, bookmarks_to_import) {
-                eprintln!("Failed to import bookmarks: {}", e);
-            };
-            StatusCode::OK.into_response()
-        }
+    match parse_bookmarks_html(&mut connection, query.library_id, &bookmarks_html) {
+        Ok(parsed) => save_import(&mut connection, &query, "HTML", parsed),
         Err(e) => {
             eprintln!("Error parsing bookmarks: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            invalid_import(e)
         }
     }
 }
 
 pub async fn import_bookmarks_linkwarden(
     State(pool): State<Arc<Pool>>,
+    Query(query): Query<ImportQuery>,
     linkwarden_json: String,
 ) -> Response {
     let mut connection = pool.get().expect("Failed to get connection from pool");
+    if !database::library_exists(&mut connection, query.library_id).unwrap_or(false) {
+        return StatusCode::NOT_FOUND.into_response();
+    }
 
     match serde_json::from_str(&linkwarden_json) {
-        Ok(json_values) => match parse_linkwarden_json(&mut connection, &json_values) {
-            Ok((folders_to_import, bookmarks_to_import)) => {
-                if let Err(e) = database::insert_folders(&mut connection, folders_to_import) {
-                    eprintln!("Failed to import folders: {}", e);
-                };
-                if let Err(e) = database::insert_bookmarks(&mut connection, bookmarks_to_import) {
-                    eprintln!("Failed to import bookmarks: {}", e);
-                };
-                StatusCode::OK.into_response()
-            }
-            Err(e) => {
-                eprintln!("Error parsing Linkwarden JSON: {}", e);
-                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        Ok(json_values) => {
+            match parse_linkwarden_json(&mut connection, query.library_id, &json_values) {
+                Ok(parsed) => save_import(&mut connection, &query, "Linkwarden", parsed),
+                Err(e) => {
+                    eprintln!("Error parsing Linkwarden JSON: {}", e);
+                    invalid_import(e)
+                }
             }
-        },
+        }
         Err(e) => {
             eprintln!("Error parsing Linkwarden JSON: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            invalid_import(e)
         }
     }
 }
 
-fn parse_bookmarks_html(
-    mut connection: &mut DbConnection,
-    html: &str,
-) -> Result<(Vec<Folder>, Vec<Bookmark>), String> {
-    let dom = Html::parse_document(&html);
+/// Accepts a Firefox `bookmarks-*.json` backup, or the compressed `.jsonlz4` from the profile
+pub async fn import_bookmarks_firefox(
+    State(pool): State<Arc<Pool>>,
+    Query(query): Query<ImportQuery>,
+    Query(options): Query<FirefoxImportQuery>,
+    backup: Bytes,
+) -> Response {
+    let mut connection = pool.get().expect("Failed to get connection from pool");
+    if !database::library_exists(&mut connection, query.library_id).unwrap_or(false) {
+        return StatusCode::NOT_FOUND.into_response();
+    }
 
-    let mut folders_to_import: Vec<Folder> = Vec::new();
-    let mut bookmarks_to_import: Vec<Bookmark> = Vec::new();
+    let json_values = match decode_firefox_backup(&backup) {
+        Ok(json_values) => json_values,
+        Err(e) => {
+            eprintln!("Error reading Firefox backup: {}", e);
+            return invalid_import(e);
+        }
+    };
+    let parsed = parse_firefox_json(
+        &mut connection,
+        query.library_id,
+        options.roots,
+        &json_values,
+    );
+    save_import(&mut connection, &query, "Firefox", parsed)
+}
+
+/// Accepts the `places.sqlite` database from a Firefox profile directory
+pub async fn import_bookmarks_places(
+    State(pool): State<Arc<Pool>>,
+    Query(query): Query<ImportQuery>,
+    Query(options): Query<FirefoxImportQuery>,
+    places_sqlite: Bytes,
+) -> Response {
+    let mut connection = pool.get().expect("Failed to get connection from pool");
+    if !database::library_exists(&mut connection, query.library_id).unwrap_or(false) {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    let json_values = match read_places_database(&places_sqlite) {
+        Ok(json_values) => json_values,
+        Err(e) => {
+            eprintln!("Error reading places.sqlite: {}", e);
+            return invalid_import(e);
+        }
+    };
+    let parsed = parse_firefox_json(
+        &mut connection,
+        query.library_id,
+        options.roots,
+        &json_values,
+    );
+    save_import(&mut connection, &query, "Firefox", parsed)
+}
+
+/// Accepts the `Bookmarks` file from a Chrome, Edge, Brave or Vivaldi profile
+pub async fn import_bookmarks_chromium(
+    State(pool): State<Arc<Pool>>,
+    Query(query): Query<ImportQuery>,
+    Query(options): Query<ChromiumImportQuery>,
+    bookmarks_json: String,
+) -> Response {
+    let mut connection = pool.get().expect("Failed to get connection from pool");
+    if !database::library_exists(&mut connection, query.library_id).unwrap_or(false) {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    match serde_json::from_str(&bookmarks_json) {
+        Ok(json_values) => {
+            let parsed = parse_chromium_json(
+                &mut connection,
+                query.library_id,
+                options.bookmark_bar,
+                &json_values,
+            );
+            save_import(&mut connection, &query, "Chromium", parsed)
+        }
+        Err(e) => {
+            eprintln!("Error parsing Chromium JSON: {}", e);
+            invalid_import(e)
+        }
+    }
+}
+
+pub async fn import_bookmarks_pinboard(
+    State(pool): State<Arc<Pool>>,
+    Query(query): Query<ImportQuery>,
+    Query(options): Query<PinboardImportQuery>,
+    pinboard_json: String,
+) -> Response {
+    let mut connection = pool.get().expect("Failed to get connection from pool");
+    if !database::library_exists(&mut connection, query.library_id).unwrap_or(false) {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    let folder_tags: Vec<String> = options
+        .folder_tags
+        .unwrap_or_default()
+        .split(',')
+        .map(|tag| tag.trim().to_string())
+        .filter(|tag| !tag.is_empty())
+        .collect();
+    match serde_json::from_str(&pinboard_json) {
+        Ok(json_values) => {
+            let parsed = parse_pinboard_json(
+                &mut connection,
+                query.library_id,
+                &folder_tags,
+                &json_values,
+            );
+            save_import(&mut connection, &query, "Pinboard", parsed)
+        }
+        Err(e) => {
+            eprintln!("Error parsing Pinboard JSON: {}", e);
+            invalid_import(e)
+        }
+    }
+}
+
+pub async fn import_bookmarks_pocket(
+    State(pool): State<Arc<Pool>>,
+    Query(query): Query<ImportQuery>,
+    pocket_csv: String,
+) -> Response {
+    let mut connection = pool.get().expect("Failed to get connection from pool");
+    if !database::library_exists(&mut connection, query.library_id).unwrap_or(false) {
+        return StatusCode::NOT_FOUND.into_response();
+    }
 
-    let mut bookmark_id_counter =
-        database::get_highest_bookmark_id(&mut connection).unwrap_or(0) + 1;
-    let mut folder_id_counter = database::get_highest_folder_id(&mut connection).unwrap_or(0) + 1;
+    match parse_pocket_csv(&mut connection, query.library_id, &pocket_csv) {
+        Ok(parsed) => save_import(&mut connection, &query, "Pocket", parsed),
+        Err(e) => {
+            eprintln!("Error parsing Pocket CSV: {}", e);
+            invalid_import(e)
+        }
+    }
+}
+
+pub async fn import_bookmarks_instapaper(
+    State(pool): State<Arc<Pool>>,
+    Query(query): Query<ImportQuery>,
+    instapaper_csv: String,
+) -> Response {
+    let mut connection = pool.get().expect("Failed to get connection from pool");
+    if !database::library_exists(&mut connection, query.library_id).unwrap_or(false) {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    match parse_instapaper_csv(&mut connection, query.library_id, &instapaper_csv) {
+        Ok(parsed) => save_import(&mut connection, &query, "Instapaper", parsed),
+        Err(e) => {
+            eprintln!("Error parsing Instapaper CSV: {}", e);
+            invalid_import(e)
+        }
+    }
+}
+
+/// Raindrop's HTML export is a Netscape file, so this is only needed for its CSV export
+pub async fn import_bookmarks_raindrop(
+    State(pool): State<Arc<Pool>>,
+    Query(query): Query<ImportQuery>,
+    raindrop_csv: String,
+) -> Response {
+    let mut connection = pool.get().expect("Failed to get connection from pool");
+    if !database::library_exists(&mut connection, query.library_id).unwrap_or(false) {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    match parse_raindrop_csv(&mut connection, query.library_id, &raindrop_csv) {
+        Ok(parsed) => save_import(&mut connection, &query, "Raindrop", parsed),
+        Err(e) => {
+            eprintln!("Error parsing Raindrop CSV: {}", e);
+            invalid_import(e)
+        }
+    }
+}
+
+/// Accepts Safari's `Bookmarks.plist`, either binary as it is on disk or converted to XML
+pub async fn import_bookmarks_safari(
+    State(pool): State<Arc<Pool>>,
+    Query(query): Query<ImportQuery>,
+    Query(options): Query<SafariImportQuery>,
+    bookmarks_plist: Bytes,
+) -> Response {
+    let mut connection = pool.get().expect("Failed to get connection from pool");
+    if !database::library_exists(&mut connection, query.library_id).unwrap_or(false) {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    match plist::Value::from_reader(Cursor::new(bookmarks_plist)) {
+        Ok(plist_value) => {
+            let parsed = parse_safari_plist(
+                &mut connection,
+                query.library_id,
+                options.bookmarks_bar,
+                options.reading_list,
+                &plist_value,
+            );
+            save_import(&mut connection, &query, "Safari", parsed)
+        }
+        Err(e) => {
+            eprintln!("Error parsing Safari plist: {}", e);
+            invalid_import(e)
+        }
+    }
+}
+
+pub async fn import_bookmarks_xbel(
+    State(pool): State<Arc<Pool>>,
+    Query(query): Query<ImportQuery>,
+    xbel: String,
+) -> Response {
+    let mut connection = pool.get().expect("Failed to get connection from pool");
+    if !database::library_exists(&mut connection, query.library_id).unwrap_or(false) {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    match parse_xbel(&mut connection, query.library_id, &xbel) {
+        Ok(parsed) => save_import(&mut connection, &query, "XBEL", parsed),
+        Err(e) => {
+            eprintln!("Error parsing XBEL: {}", e);
+            invalid_import(e)
+        }
+    }
+}
+
+pub async fn import_bookmarks_opml(
+    State(pool): State<Arc<Pool>>,
+    Query(query): Query<ImportQuery>,
+    opml: String,
+) -> Response {
+    let mut connection = pool.get().expect("Failed to get connection from pool");
+    if !database::library_exists(&mut connection, query.library_id).unwrap_or(false) {
+        return StatusCode::NOT_FOUND.into_response();
+    }
 
-    let folder_selector = Selector::parse("DL > DT > H3")
-        .map_err(|e| format!("Failed to create folder_selector: {}", e))?;
-    let bookmark_selector = Selector::parse("DT > A")
-        .map_err(|e| format!("Failed to create bookmark_selector selector: {}", e))?;
+    match parse_opml(&mut connection, query.library_id, &opml) {
+        Ok(parsed) => save_import(&mut connection, &query, "OPML", parsed),
+        Err(e) => {
+            eprintln!("Error parsing OPML: {}", e);
+            invalid_import(e)
+        }
+    }
+}
 
-    for folder_element in dom.select(&folder_selector) {
-        let folder_name = folder_element.text().collect::<String>();
+/// Inserts what an importer parsed in one go according to the import options, or on a dry
+/// run only reports what that would do. `source` names the wrapper folder if one is asked for.
+fn save_import(
+    connection: &mut DbConnection,
+    query: &ImportQuery,
+    source: &str,
+    mut parsed: ParsedBookmarks,
+) -> Response {
+    let library_id = query.library_id;
+    if let Some(folder_id) = query.folder_id {
+        if !database::folder_in_library(connection, folder_id, library_id).unwrap_or(false) {
+            return StatusCode::NOT_FOUND.into_response();
+        }
+    }
 
-        let parent_folder_id = match find_parent_folder(&folder_element, &folders_to_import) {
-            Some(parent) => Some(parent.id),
-            None => None,
+    let result = connection.transaction(|connection| {
+        let wrapper = if query.wrap {
+            Some(wrapper_folder(connection, query, source, &parsed)?)
+        } else {
+            None
         };
+        if let Some(target_id) = wrapper
+            .as_ref()
+            .map(|wrapper| wrapper.id)
+            .or(query.folder_id)
+        {
+            move_roots_into(
+                connection,
+                library_id,
+                target_id,
+                wrapper.is_some(),
+                &mut parsed,
+            )?;
+        }
+        if let Some(wrapper) = wrapper {
+            parsed.folders.insert(0, wrapper);
+        }
 
-        let created = folder_element
-            .value()
-            .attr("add_date")
-            .and_then(|timestamp| timestamp.parse::<i64>().ok())
-            .map(|unix_timestamp| time::OffsetDateTime::from_unix_timestamp(unix_timestamp).ok())
-            .flatten()
-            .unwrap_or(OffsetDateTime::now_local().unwrap_or(OffsetDateTime::now_utc()));
-
-        folders_to_import.push(Folder {
-            id: folder_id_counter,
-            name: folder_name,
-            created,
-            parent_id: parent_folder_id,
-            favorite: false,
+        let (planned, duplicates) = match query.mode {
+            ImportMode::Merge => merge_import(connection, library_id, false, parsed)?,
+            ImportMode::SkipDuplicates => merge_import(connection, library_id, true, parsed)?,
+            ImportMode::Append | ImportMode::Replace => (parsed, Vec::new()),
+        };
+        let report = import_report(connection, query, &planned, duplicates)?;
+        if query.dry_run {
+            return Ok(report);
+        }
+
+        if query.mode == ImportMode::Replace {
+            database::trash_library_items(connection, library_id, query.folder_id)?;
+        }
+        database::insert_import(
+            connection,
+            planned.folders,
+            planned.bookmarks,
+            planned.tags,
+            planned.separators,
+        )?;
+        Ok::<_, diesel::result::Error>(report)
+    });
+
+    match result {
+        Ok(report) => Json(report).into_response(),
+        Err(e) => {
+            eprintln!("Failed to import bookmarks: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// A file that can't be read is the client's mistake, the reason goes back with the 400
+fn invalid_import(error: impl std::fmt::Display) -> Response {
+    (
+        StatusCode::BAD_REQUEST,
+        Json(json!({ "error": error.to_string() })),
+    )
+        .into_response()
+}
+
+/// A new folder like "Imported from Chromium 2026-10-18" at the end of the destination
+fn wrapper_folder(
+    connection: &mut DbConnection,
+    query: &ImportQuery,
+    source: &str,
+    parsed: &ParsedBookmarks,
+) -> Result<Folder, diesel::result::Error> {
+    let now = OffsetDateTime::now_local().unwrap_or(OffsetDateTime::now_utc());
+    let lowest_id = parsed
+        .folders
+        .iter()
+        .map(|folder| folder.id)
+        .min()
+        .unwrap_or(0)
+        .min(0);
+
+    Ok(Folder {
+        id: lowest_id - 1,
+        name: format!("Imported from {} {}", source, now.date()),
+        created: now,
+        parent_id: query.folder_id,
+        favorite: false,
+        position: database::next_folder_position(connection, query.library_id, query.folder_id)?,
+        favorite_position: 0,
+        deleted_at: None,
+        updated: now,
+        library_id: query.library_id,
+    })
+}
+
+/// Puts the imported top-level items into `target_id`, after whatever is already in there
+fn move_roots_into(
+    connection: &mut DbConnection,
+    library_id: i32,
+    target_id: i32,
+    target_is_new: bool,
+    parsed: &mut ParsedBookmarks,
+) -> Result<(), diesel::result::Error> {
+    let (mut folder_position, mut bookmark_position) = if target_is_new {
+        (0, 0)
+    } else {
+        (
+            database::next_folder_position(connection, library_id, Some(target_id))?,
+            database::next_bookmark_position(connection, library_id, Some(target_id))?,
+        )
+    };
+
+    let mut root_folders: Vec<&mut Folder> = parsed
+        .folders
+        .iter_mut()
+        .filter(|folder| folder.parent_id.is_none())
+        .collect();
+    root_folders.sort_by_key(|folder| folder.position);
+    for folder in root_folders {
+        folder.parent_id = Some(target_id);
+        folder.position = folder_position;
+        folder_position += 1;
+    }
+
+    // Separators share positions with bookmarks, so they're renumbered together
+    let mut root_positions: Vec<&mut i32> = Vec::new();
+    for bookmark in parsed.bookmarks.iter_mut() {
+        if bookmark.folder_id.is_none() {
+            bookmark.folder_id = Some(target_id);
+            root_positions.push(&mut bookmark.position);
+        }
+    }
+    for separator in parsed.separators.iter_mut() {
+        if separator.folder_id.is_none() {
+            separator.folder_id = Some(target_id);
+            root_positions.push(&mut separator.position);
+        }
+    }
+    root_positions.sort_by_key(|position| **position);
+    for position in root_positions {
+        *position = bookmark_position;
+        bookmark_position += 1;
+    }
+
+    Ok(())
+}
+
+/// Imported folders whose path of names already exists are folded into the existing folder,
+/// and bookmarks are left out when their URL is already in their folder (or anywhere in the
+/// library when `library_wide`). Separators only survive in folders that are new.
+/// Returns what is left to insert and the bookmarks that were left out.
+fn merge_import(
+    connection: &mut DbConnection,
+    library_id: i32,
+    library_wide: bool,
+    mut parsed: ParsedBookmarks,
+) -> Result<(ParsedBookmarks, Vec<Bookmark>), diesel::result::Error> {
+    let imported_ids: HashSet<i32> = parsed.folders.iter().map(|folder| folder.id).collect();
+    let mut existing_folders = database::get_all_folders(connection, library_id)?;
+    existing_folders.sort_by_key(|folder| (folder.position, folder.id));
+    let mut existing_folder_ids: HashMap<(Option<i32>, String), i32> = HashMap::new();
+    for folder in existing_folders {
+        existing_folder_ids
+            .entry((folder.parent_id, folder.name))
+            .or_insert(folder.id);
+    }
+    let mut existing_urls: HashSet<(Option<i32>, String)> =
+        database::get_all_bookmarks(connection, library_id)?
+            .into_iter()
+            .map(|bookmark| {
+                let folder_id = if library_wide {
+                    None
+                } else {
+                    bookmark.folder_id
+                };
+                (folder_id, bookmark.url)
+            })
+            .collect();
+
+    // Parents have to be matched before their children, and not every format lists them first
+    let parent_ids: HashMap<i32, Option<i32>> = parsed
+        .folders
+        .iter()
+        .map(|folder| (folder.id, folder.parent_id))
+        .collect();
+    parsed
+        .folders
+        .sort_by_cached_key(|folder| folder_depth(folder.id, &parent_ids));
+
+    // Imported folder ids that turned out to exist, mapped to the existing ids
+    let mut merged_ids: HashMap<i32, i32> = HashMap::new();
+    // New items in existing folders go after what's already there
+    let mut folder_positions: HashMap<Option<i32>, i32> = HashMap::new();
+    let mut bookmark_positions: HashMap<Option<i32>, i32> = HashMap::new();
+
+    let mut kept_folders = Vec::new();
+    for mut folder in parsed.folders {
+        let parent_exists = folder.parent_id.is_none_or(|parent_id| {
+            merged_ids.contains_key(&parent_id) || !imported_ids.contains(&parent_id)
         });
+        folder.parent_id = folder
+            .parent_id
+            .map(|parent_id| *merged_ids.get(&parent_id).unwrap_or(&parent_id));
+        if parent_exists {
+            if let Some(existing_id) =
+                existing_folder_ids.get(&(folder.parent_id, folder.name.clone()))
+            {
+                merged_ids.insert(folder.id, *existing_id);
+                continue;
+            }
+            if folder.parent_id.is_some() {
+                let position = folder_positions.entry(folder.parent_id).or_insert_with(|| {
+                    database::next_folder_position(connection, library_id, folder.parent_id)
+                        .unwrap_or(0)
+                });
+                folder.position = *position;
+                *position += 1;
+            }
+        }
+        kept_folders.push(folder);
+    }
 
-        folder_id_counter += 1;
+    let mut kept_bookmarks = Vec::new();
+    let mut duplicates = Vec::new();
+    for mut bookmark in parsed.bookmarks {
+        let in_existing_folder = bookmark.folder_id.is_some_and(|folder_id| {
+            merged_ids.contains_key(&folder_id) || !imported_ids.contains(&folder_id)
+        });
+        bookmark.folder_id = bookmark
+            .folder_id
+            .map(|folder_id| *merged_ids.get(&folder_id).unwrap_or(&folder_id));
+        let folder_id = if library_wide {
+            None
+        } else {
+            bookmark.folder_id
+        };
+        if !existing_urls.insert((folder_id, bookmark.url.clone())) {
+            parsed.tags.remove(&bookmark.id);
+            duplicates.push(bookmark);
+            continue;
+        }
+        if in_existing_folder {
+            let position = bookmark_positions
+                .entry(bookmark.folder_id)
+                .or_insert_with(|| {
+                    database::next_bookmark_position(connection, library_id, bookmark.folder_id)
+                        .unwrap_or(0)
+                });
+            bookmark.position = *position;
+            *position += 1;
+        }
+        kept_bookmarks.push(bookmark);
     }
 
-    for bookmark_element in dom.select(&bookmark_selector) {
-        if let Some(url) = bookmark_element.value().attr("href") {
-            let name = bookmark_element.text().collect::<String>();
-            let favicon = bookmark_element.value().attr("icon").map(String::from);
-            let favicon_url = bookmark_element.value().attr("icon_uri").map(String::from);
+    parsed.separators.retain(|separator| {
+        separator.folder_id.is_some_and(|folder_id| {
+            imported_ids.contains(&folder_id) && !merged_ids.contains_key(&folder_id)
+        })
+    });
 
-            // Find parent folder
-            let folder_id = match find_parent_folder(&bookmark_element, &folders_to_import) {
-                Some(parent) => Some(parent.id),
-                None => None,
-            };
+    Ok((
+        ParsedBookmarks {
+            folders: kept_folders,
+            bookmarks: kept_bookmarks,
+            ..parsed
+        },
+        duplicates,
+    ))
+}
 
-            let created = bookmark_element
-                .value()
-                .attr("add_date")
-                .and_then(|timestamp| timestamp.parse::<i64>().ok())
-                .map(|unix_timestamp| {
-                    time::OffsetDateTime::from_unix_timestamp(unix_timestamp).ok()
-                })
-                .flatten()
-                .unwrap_or(OffsetDateTime::now_local().unwrap_or(OffsetDateTime::now_utc()));
+/// Describes the import against the library as it will be afterwards
+fn import_report(
+    connection: &mut DbConnection,
+    query: &ImportQuery,
+    planned: &ParsedBookmarks,
+    duplicates: Vec<Bookmark>,
+) -> Result<ImportReport, diesel::result::Error> {
+    let mut existing_folders = database::get_all_folders(connection, query.library_id)?;
+    let mut existing_bookmarks = database::get_all_bookmarks(connection, query.library_id)?;
+    if query.mode == ImportMode::Replace {
+        // Replacing clears out where the import goes, the top level or the chosen folder
+        let existing_parent_ids: HashMap<i32, Option<i32>> = existing_folders
+            .iter()
+            .map(|folder| (folder.id, folder.parent_id))
+            .collect();
+        let cleared_ids: HashSet<i32> = existing_folders
+            .iter()
+            .map(|folder| folder.id)
+            .filter(|folder_id| match query.folder_id {
+                Some(target_id) => is_inside(*folder_id, target_id, &existing_parent_ids),
+                None => true,
+            })
+            .collect();
+        existing_folders.retain(|folder| !cleared_ids.contains(&folder.id));
+        existing_bookmarks.retain(|bookmark| {
+            bookmark.folder_id != query.folder_id
+                && !bookmark
+                    .folder_id
+                    .is_some_and(|folder_id| cleared_ids.contains(&folder_id))
+        });
+    }
+    let existing_bookmark_count = existing_bookmarks.len();
+    let all_folders: HashMap<i32, &Folder> = existing_folders
+        .iter()
+        .chain(&planned.folders)
+        .map(|folder| (folder.id, folder))
+        .collect();
+    let parent_ids: HashMap<i32, Option<i32>> = all_folders
+        .values()
+        .map(|folder| (folder.id, folder.parent_id))
+        .collect();
 
-            bookmarks_to_import.push(Bookmark {
-                id: bookmark_id_counter,
-                name,
-                url: url.to_string(),
-                favicon,
-                favicon_url,
-                created,
-                folder_id,
-                favorite: false,
-            });
+    let folder_path = |folder_id: i32| {
+        let mut names = Vec::new();
+        let mut current = Some(folder_id);
+        while let Some(folder) = current.and_then(|id| all_folders.get(&id)) {
+            names.push(folder.name.as_str());
+            current = folder.parent_id;
+            // A broken parent chain must not loop forever
+            if names.len() > all_folders.len() {
+                break;
+            }
+        }
+        names.reverse();
+        names.join("/")
+    };
+    let item = |bookmark: &Bookmark| ImportedItem {
+        name: bookmark.name.clone(),
+        url: bookmark.url.clone(),
+        folder: bookmark.folder_id.map(folder_path),
+    };
+
+    Ok(ImportReport {
+        dry_run: query.dry_run,
+        imported: ImportCounts {
+            folders: planned.folders.len(),
+            bookmarks: planned.bookmarks.len(),
+        },
+        skipped: duplicates.len() + planned.invalid.len(),
+        folders: planned
+            .folders
+            .iter()
+            .map(|folder| folder_path(folder.id))
+            .collect(),
+        bookmarks: planned.bookmarks.iter().map(item).collect(),
+        duplicates: duplicates.iter().map(item).collect(),
+        invalid: planned.invalid.clone(),
+        warnings: planned.warnings.clone(),
+        tree: TreeStats {
+            folders: all_folders.len(),
+            bookmarks: existing_bookmark_count + planned.bookmarks.len(),
+            depth: all_folders
+                .keys()
+                .map(|folder_id| folder_depth(*folder_id, &parent_ids))
+                .max()
+                .unwrap_or(0),
+        },
+    })
+}
+
+/// Whether a folder is somewhere below `ancestor_id`
+fn is_inside(folder_id: i32, ancestor_id: i32, parent_ids: &HashMap<i32, Option<i32>>) -> bool {
+    let mut current = parent_ids.get(&folder_id).copied().flatten();
+    let mut steps = 0;
+    while let Some(parent_id) = current {
+        if parent_id == ancestor_id {
+            return true;
+        }
+        steps += 1;
+        if steps > parent_ids.len() {
+            break;
+        }
+        current = parent_ids.get(&parent_id).copied().flatten();
+    }
+    false
+}
 
-            bookmark_id_counter += 1;
+/// How many folders deep a folder is, 1 at the top level
+fn folder_depth(folder_id: i32, parent_ids: &HashMap<i32, Option<i32>>) -> usize {
+    let mut depth = 1;
+    let mut current = parent_ids.get(&folder_id).copied().flatten();
+    while let Some(parent_id) = current {
+        depth += 1;
+        if depth > parent_ids.len() {
+            break;
         }
+        current = parent_ids.get(&parent_id).copied().flatten();
+    }
+    depth
+}
+
+/// What an importer read from a file. Its items have temporary negative ids, so they can't be
+/// mistaken for existing ones, until the database assigns real ids when they're inserted.
+struct ParsedBookmarks {
+    folders: Vec<Folder>,
+    bookmarks: Vec<Bookmark>,
+    /// Each imported bookmark's tags keyed by bookmark id
+    tags: HashMap<i32, Vec<String>>,
+    separators: Vec<NewSeparator>,
+    /// Entries that were left out because they couldn't be read
+    invalid: Vec<String>,
+    /// Entries that were imported despite a problem
+    warnings: Vec<String>,
+}
+
+fn parse_bookmarks_html(
+    connection: &mut DbConnection,
+    library_id: i32,
+    html: &str,
+) -> Result<ParsedBookmarks, String> {
+    let dom = Html::parse_document(html);
+    let list_selector =
+        Selector::parse("dl").map_err(|e| format!("Failed to create list_selector: {}", e))?;
+    if dom.select(&list_selector).next().is_none() {
+        return Err("No bookmark list found".to_string());
     }
 
-    Ok((folders_to_import, bookmarks_to_import))
+    let mut walker = NetscapeWalker {
+        builder: ImportBuilder::new(connection, library_id),
+        toolbar_folder_ids: HashSet::new(),
+    };
+    walker.walk_list(dom.root_element(), None);
+
+    Ok(walker.builder.finish(connection))
+}
+
+/// Follows the nesting of the file rather than folder names, so folders can share a name.
+/// Browsers never close `<DT>` or `<p>`, which leaves a folder's `<DL>` inside its `<DT>`,
+/// inside a `<DD>` holding the folder's description, or next to the `<DT>` depending on
+/// the markup, so the walk looks through those for the list that follows each `<H3>`.
+struct NetscapeWalker {
+    builder: ImportBuilder,
+    /// Whatever sits in the browser's toolbar folder goes into the favorites bar
+    toolbar_folder_ids: HashSet<i32>,
+}
+
+/// The latest folder and bookmark seen at one level, a `<DL>` belongs to that folder and
+/// a `<DD>` describes that bookmark
+#[derive(Default)]
+struct NetscapeLevel {
+    last_folder_id: Option<i32>,
+    last_bookmark_id: Option<i32>,
 }
 
-fn find_parent_folder<'a>(element: &ElementRef, folders: &'a [Folder]) -> Option<&'a Folder> {
-    let mut current = element.parent()?;
+impl NetscapeWalker {
+    fn walk_list(&mut self, list: ElementRef, parent_id: Option<i32>) {
+        let mut level = NetscapeLevel::default();
+        self.walk_children(list, parent_id, &mut level);
+    }
 
-    while let Some(parent) = current.parent() {
-        if let Some(element) = parent.value().as_element() {
-            if element.name() == "dl" {
-                if let Some(header) = parent
-                    .parent()?
-                    .first_child()?
-                    .first_child()?
-                    .value()
-                    .as_text()
-                {
-                    return folders.iter().find(|f| f.name == header.trim());
+    fn walk_children(
+        &mut self,
+        node: ElementRef,
+        parent_id: Option<i32>,
+        level: &mut NetscapeLevel,
+    ) {
+        for child in node.children().filter_map(ElementRef::wrap) {
+            match child.value().name() {
+                "h3" => {
+                    level.last_folder_id = Some(self.add_folder(child, parent_id));
+                    level.last_bookmark_id = None;
+                }
+                "a" => {
+                    level.last_bookmark_id = self.add_bookmark(child, parent_id);
+                    level.last_folder_id = None;
+                }
+                "dl" => {
+                    // A folder has a single list, anything after it is back at this level
+                    let list_parent_id = level.last_folder_id.take().or(parent_id);
+                    self.walk_list(child, list_parent_id);
+                }
+                "dd" => {
+                    if let Some(bookmark_id) = level.last_bookmark_id.take() {
+                        self.describe_bookmark(bookmark_id, child);
+                    }
+                    self.walk_children(child, parent_id, level);
                 }
+                _ => self.walk_children(child, parent_id, level),
+            }
+        }
+    }
+
+    fn add_folder(&mut self, header: ElementRef, parent_id: Option<i32>) -> i32 {
+        let name = header.text().collect::<String>();
+        let created = parse_add_date(&mut self.builder.warnings, &name, &header);
+        let updated = parse_last_modified(&header).unwrap_or(created);
+        let folder_id = self.builder.add_folder(name, parent_id, created, updated);
+
+        if header
+            .value()
+            .attr("personal_toolbar_folder")
+            .is_some_and(|toolbar| toolbar.eq_ignore_ascii_case("true"))
+        {
+            self.toolbar_folder_ids.insert(folder_id);
+        }
+        if parent_id.is_some_and(|parent_id| self.toolbar_folder_ids.contains(&parent_id)) {
+            self.builder.favorite_folder_ids.push(folder_id);
+        }
+        folder_id
+    }
+
+    fn add_bookmark(&mut self, link: ElementRef, parent_id: Option<i32>) -> Option<i32> {
+        let element = link.value();
+        let Some(url) = element.attr("href") else {
+            self.builder.invalid.push(format!(
+                "Link \"{}\" has no HREF",
+                link.text().collect::<String>().trim()
+            ));
+            return None;
+        };
+        let name = link.text().collect::<String>();
+        let created = parse_add_date(&mut self.builder.warnings, &name, &link);
+
+        let mut bookmark = ImportedBookmark::new(name, url.to_string(), parent_id, created);
+        bookmark.updated = parse_last_modified(&link).unwrap_or(created);
+        bookmark.favicon = element.attr("icon").map(String::from);
+        bookmark.favicon_url = element.attr("icon_uri").map(String::from);
+        bookmark.keyword = element
+            .attr("shortcuturl")
+            .filter(|keyword| !keyword.trim().is_empty())
+            .map(String::from);
+        bookmark.tags = element
+            .attr("tags")
+            .map(|tags| tags.split(',').map(|tag| tag.trim().to_string()).collect())
+            .unwrap_or_default();
+        bookmark.read_later = element
+            .attr("toread")
+            .is_some_and(|toread| toread == "1" || toread.eq_ignore_ascii_case("true"));
+
+        let bookmark_id = self.builder.add_bookmark(bookmark);
+        if parent_id.is_some_and(|parent_id| self.toolbar_folder_ids.contains(&parent_id)) {
+            self.builder.favorite_bookmark_ids.push(bookmark_id);
+        }
+        Some(bookmark_id)
+    }
+
+    fn describe_bookmark(&mut self, bookmark_id: i32, dd: ElementRef) {
+        let mut description = String::new();
+        description_text(dd, &mut description);
+        let description = description.trim();
+        if description.is_empty() {
+            return;
+        }
+        if let Some(bookmark) = self
+            .builder
+            .bookmarks
+            .iter_mut()
+            .find(|bookmark| bookmark.id == bookmark_id)
+        {
+            bookmark.description = Some(description.to_string());
+        }
+    }
+}
+
+/// All the text in a `<DD>`, including inside markup like `<b>`, up to a list that ended up
+/// inside it. Returns false once that list is reached.
+fn description_text(element: ElementRef, description: &mut String) -> bool {
+    for child in element.children() {
+        if let Some(text) = child.value().as_text() {
+            description.push_str(text);
+        } else if let Some(child) = ElementRef::wrap(child) {
+            if child.value().name() == "dl" || !description_text(child, description) {
+                return false;
             }
         }
-        current = parent;
     }
+    true
+}
 
-    None
+/// Hands out consecutive positions per parent folder in the order items are imported
+fn next_position(positions: &mut HashMap<Option<i32>, i32>, parent_id: Option<i32>) -> i32 {
+    let position = positions.entry(parent_id).or_insert(0);
+    *position += 1;
+    *position - 1
 }
 
 fn parse_linkwarden_json(
-    mut connection: &mut DbConnection,
+    connection: &mut DbConnection,
+    library_id: i32,
     json_data: &Value,
-) -> Result<(Vec<Folder>, Vec<Bookmark>), String> {
+) -> Result<ParsedBookmarks, String> {
     let mut folders_to_import: Vec<Folder> = Vec::new();
     let mut bookmarks_to_import: Vec<Bookmark> = Vec::new();
+    let mut tags_to_import: HashMap<i32, Vec<String>> = HashMap::new();
 
-    let mut bookmark_id_counter =
-        database::get_highest_bookmark_id(&mut connection).unwrap_or(0) + 1;
-    let mut folder_id_counter = database::get_highest_folder_id(&mut connection).unwrap_or(0) + 1;
+    let mut bookmark_id_counter = -1;
+    let mut folder_id_counter = -1;
 
     let mut folder_id_counterparts: HashMap<i32, i32> = HashMap::new();
+    let mut invalid: Vec<String> = Vec::new();
+    let mut warnings: Vec<String> = Vec::new();
+    let mut bookmark_positions: HashMap<Option<i32>, i32> = HashMap::new();
 
     if let Some(collections) = json_data["collections"].as_array() {
         for collection in collections {
@@ -198,7 +949,14 @@ fn parse_linkwarden_json(
                 Some(id) => Some(id as i32),
                 None => None,
             };
-            let folder_created = parse_created_date(collection["createdAt"].as_str());
+            let folder_created =
+                parse_created_date(&mut warnings, folder_name, collection["createdAt"].as_str());
+            let folder_updated = match collection["updatedAt"].as_str() {
+                Some(updated_at) => {
+                    parse_created_date(&mut warnings, folder_name, Some(updated_at))
+                }
+                None => folder_created,
+            };
 
             folders_to_import.push(Folder {
                 id: folder_id_counter,
@@ -206,6 +964,11 @@ fn parse_linkwarden_json(
                 created: folder_created,
                 parent_id: folder_parent_id,
                 favorite: false,
+                position: 0,
+                favorite_position: 0,
+                deleted_at: None,
+                updated: folder_updated,
+                library_id,
             });
 
             folder_id_counterparts.insert(folder_id, folder_id_counter);
@@ -217,7 +980,18 @@ fn parse_linkwarden_json(
                         if bookmark_name.trim().is_empty() {
                             bookmark_name = "Missing Name";
                         }
-                        let bookmark_created = parse_created_date(collection["createdAt"].as_str());
+                        let bookmark_created = folder_created;
+                        let bookmark_updated = match link["updatedAt"].as_str() {
+                            Some(updated_at) => {
+                                parse_created_date(&mut warnings, bookmark_name, Some(updated_at))
+                            }
+                            None => bookmark_created,
+                        };
+                        warnings.extend(url_warning(bookmark_name, bookmark_url));
+                        let bookmark_description = link["description"]
+                            .as_str()
+                            .filter(|description| !description.trim().is_empty())
+                            .map(String::from);
 
                         bookmarks_to_import.push(Bookmark {
                             id: bookmark_id_counter,
@@ -228,17 +1002,56 @@ fn parse_linkwarden_json(
                             created: bookmark_created,
                             folder_id: Some(folder_id_counter),
                             favorite: false,
+                            description: bookmark_description,
+                            position: next_position(
+                                &mut bookmark_positions,
+                                Some(folder_id_counter),
+                            ),
+                            favorite_position: 0,
+                            deleted_at: None,
+                            visit_count: 0,
+                            last_visited: None,
+                            read_later: false,
+                            read_at: None,
+                            updated: bookmark_updated,
+                            library_id,
+                            keyword: None,
+                            queued_at: None,
                         });
 
-                        bookmark_id_counter += 1;
+                        if let Some(tags) = link["tags"].as_array() {
+                            let tags: Vec<String> = tags
+                                .iter()
+                                .filter_map(|tag| tag["name"].as_str())
+                                .map(String::from)
+                                .collect();
+                            if !tags.is_empty() {
+                                tags_to_import.insert(bookmark_id_counter, tags);
+                            }
+                        }
+
+                        bookmark_id_counter -= 1;
+                    } else {
+                        invalid.push(format!(
+                            "Link \"{}\" in collection \"{}\" has no URL",
+                            link["name"].as_str().unwrap_or(""),
+                            folder_name
+                        ));
                     }
                 }
             }
 
-            folder_id_counter += 1;
+            folder_id_counter -= 1;
         }
     }
 
+    // Keep the order of the file, after whatever is already at the top level
+    let mut folder_positions: HashMap<Option<i32>, i32> = HashMap::new();
+    folder_positions.insert(
+        None,
+        database::next_folder_position(connection, library_id, None).unwrap_or(0),
+    );
+
     // Re-assign parent_id to proper Nadamark folder id
     for folder in &mut folders_to_import {
         if folder.parent_id.is_some() {
@@ -247,20 +1060,2165 @@ fn parse_linkwarden_json(
                 .copied();
             folder.parent_id = new_parent_id;
         }
+        folder.position = next_position(&mut folder_positions, folder.parent_id);
     }
 
-    Ok((folders_to_import, bookmarks_to_import))
+    Ok(ParsedBookmarks {
+        folders: folders_to_import,
+        bookmarks: bookmarks_to_import,
+        tags: tags_to_import,
+        separators: Vec::new(),
+        invalid,
+        warnings,
+    })
 }
 
-fn parse_created_date(date_str: Option<&str>) -> OffsetDateTime {
-    match date_str {
-        Some(ds) => match OffsetDateTime::parse(ds, &Rfc3339) {
-            Ok(dt) => dt,
-            Err(e) => {
-                eprintln!("Error converting DateTime: {}", e);
-                time::OffsetDateTime::now_local().unwrap_or(time::OffsetDateTime::now_utc())
-            }
-        },
-        None => time::OffsetDateTime::now_local().unwrap_or(time::OffsetDateTime::now_utc()),
+/// A bookmark read from an import file, before it gets an id and a position
+struct ImportedBookmark {
+    name: String,
+    url: String,
+    folder_id: Option<i32>,
+    created: OffsetDateTime,
+    updated: OffsetDateTime,
+    favicon: Option<String>,
+    favicon_url: Option<String>,
+    description: Option<String>,
+    keyword: Option<String>,
+    tags: Vec<String>,
+    read_later: bool,
+}
+
+impl ImportedBookmark {
+    fn new(name: String, url: String, folder_id: Option<i32>, created: OffsetDateTime) -> Self {
+        ImportedBookmark {
+            name,
+            url,
+            folder_id,
+            created,
+            updated: created,
+            favicon: None,
+            favicon_url: None,
+            description: None,
+            keyword: None,
+            tags: Vec::new(),
+            read_later: false,
+        }
+    }
+}
+
+/// Collects parsed folders and bookmarks, handing out temporary ids and keeping the file's order
+struct ImportBuilder {
+    library_id: i32,
+    folders: Vec<Folder>,
+    bookmarks: Vec<Bookmark>,
+    tags: HashMap<i32, Vec<String>>,
+    next_folder_id: i32,
+    next_bookmark_id: i32,
+    folder_positions: HashMap<Option<i32>, i32>,
+    bookmark_positions: HashMap<Option<i32>, i32>,
+    /// Items for the favorites bar, in the order they should appear there
+    favorite_folder_ids: Vec<i32>,
+    favorite_bookmark_ids: Vec<i32>,
+    /// Read-later bookmarks the file says were already read
+    read_bookmark_ids: Vec<i32>,
+    separators: Vec<NewSeparator>,
+    /// Entries that were left out because they couldn't be read
+    invalid: Vec<String>,
+    warnings: Vec<String>,
+}
+
+impl ImportBuilder {
+    fn new(connection: &mut DbConnection, library_id: i32) -> Self {
+        // Top-level items go after whatever is already there
+        let mut folder_positions: HashMap<Option<i32>, i32> = HashMap::new();
+        folder_positions.insert(
+            None,
+            database::next_folder_position(connection, library_id, None).unwrap_or(0),
+        );
+        let mut bookmark_positions: HashMap<Option<i32>, i32> = HashMap::new();
+        bookmark_positions.insert(
+            None,
+            database::next_bookmark_position(connection, library_id, None).unwrap_or(0),
+        );
+
+        ImportBuilder {
+            library_id,
+            folders: Vec::new(),
+            bookmarks: Vec::new(),
+            tags: HashMap::new(),
+            next_folder_id: -1,
+            next_bookmark_id: -1,
+            folder_positions,
+            bookmark_positions,
+            favorite_folder_ids: Vec::new(),
+            favorite_bookmark_ids: Vec::new(),
+            read_bookmark_ids: Vec::new(),
+            separators: Vec::new(),
+            invalid: Vec::new(),
+            warnings: Vec::new(),
+        }
+    }
+
+    fn add_folder(
+        &mut self,
+        name: String,
+        parent_id: Option<i32>,
+        created: OffsetDateTime,
+        updated: OffsetDateTime,
+    ) -> i32 {
+        let folder_id = self.next_folder_id;
+        self.next_folder_id -= 1;
+        self.folders.push(Folder {
+            id: folder_id,
+            name,
+            created,
+            parent_id,
+            favorite: false,
+            position: next_position(&mut self.folder_positions, parent_id),
+            favorite_position: 0,
+            deleted_at: None,
+            updated,
+            library_id: self.library_id,
+        });
+        folder_id
+    }
+
+    /// Files where nothing at all could be read are probably in some other format
+    fn nothing_read(&self) -> bool {
+        self.folders.is_empty() && self.bookmarks.is_empty() && !self.invalid.is_empty()
+    }
+
+    /// Separators take the next bookmark position in their folder
+    fn add_separator(&mut self, parent_id: Option<i32>) {
+        self.separators.push(NewSeparator {
+            folder_id: parent_id,
+            position: next_position(&mut self.bookmark_positions, parent_id),
+            library_id: self.library_id,
+        });
+    }
+
+    fn add_bookmark(&mut self, bookmark: ImportedBookmark) -> i32 {
+        self.warnings
+            .extend(url_warning(&bookmark.name, &bookmark.url));
+        let bookmark_id = self.next_bookmark_id;
+        self.next_bookmark_id -= 1;
+        if !bookmark.tags.is_empty() {
+            self.tags.insert(bookmark_id, bookmark.tags);
+        }
+        self.bookmarks.push(Bookmark {
+            id: bookmark_id,
+            name: bookmark.name,
+            url: bookmark.url,
+            favicon: bookmark.favicon,
+            favicon_url: bookmark.favicon_url,
+            created: bookmark.created,
+            folder_id: bookmark.folder_id,
+            favorite: false,
+            description: bookmark.description,
+            position: next_position(&mut self.bookmark_positions, bookmark.folder_id),
+            favorite_position: 0,
+            deleted_at: None,
+            visit_count: 0,
+            last_visited: None,
+            read_later: bookmark.read_later,
+            read_at: None,
+            updated: bookmark.updated,
+            library_id: self.library_id,
+            keyword: bookmark.keyword,
+            // Files don't say when something was queued, the bookmark's own date is closest
+            queued_at: bookmark.read_later.then_some(bookmark.created),
+        });
+        bookmark_id
+    }
+
+    /// Favorites go after the existing ones, folders first like in the tree.
+    /// Files don't say when something was read, so it counts as read from the import on.
+    fn finish(mut self, connection: &mut DbConnection) -> ParsedBookmarks {
+        let now = OffsetDateTime::now_local().unwrap_or(OffsetDateTime::now_utc());
+        for bookmark_id in &self.read_bookmark_ids {
+            if let Some(bookmark) = self.bookmarks.iter_mut().find(|b| b.id == *bookmark_id) {
+                bookmark.read_at = Some(now);
+            }
+        }
+
+        let mut favorite_position =
+            database::next_favorite_position(connection, self.library_id).unwrap_or(0);
+        for folder_id in &self.favorite_folder_ids {
+            if let Some(folder) = self.folders.iter_mut().find(|f| f.id == *folder_id) {
+                folder.favorite = true;
+                folder.favorite_position = favorite_position;
+                favorite_position += 1;
+            }
+        }
+        for bookmark_id in &self.favorite_bookmark_ids {
+            if let Some(bookmark) = self.bookmarks.iter_mut().find(|b| b.id == *bookmark_id) {
+                bookmark.favorite = true;
+                bookmark.favorite_position = favorite_position;
+                favorite_position += 1;
+            }
+        }
+
+        ParsedBookmarks {
+            folders: self.folders,
+            bookmarks: self.bookmarks,
+            tags: self.tags,
+            separators: self.separators,
+            invalid: self.invalid,
+            warnings: self.warnings,
+        }
+    }
+}
+
+const MOZLZ4_MAGIC: &[u8] = b"mozLz40\0";
+
+/// Backups in the Firefox profile are LZ4 blocks behind a magic number and the decompressed size
+fn decode_firefox_backup(backup: &[u8]) -> Result<Value, String> {
+    let json = match backup.strip_prefix(MOZLZ4_MAGIC) {
+        Some(compressed) => {
+            if compressed.len() < 4 {
+                return Err("Truncated mozlz4 header".to_string());
+            }
+            let (size, block) = compressed.split_at(4);
+            let size = u32::from_le_bytes([size[0], size[1], size[2], size[3]]) as usize;
+            lz4_flex::block::decompress(block, size)
+                .map_err(|e| format!("Failed to decompress mozlz4: {}", e))?
+        }
+        None => backup.to_vec(),
+    };
+
+    serde_json::from_slice(&json).map_err(|e| format!("Failed to parse Firefox JSON: {}", e))
+}
+
+fn parse_firefox_json(
+    connection: &mut DbConnection,
+    library_id: i32,
+    roots: FirefoxRoots,
+    json_data: &Value,
+) -> ParsedBookmarks {
+    let mut builder = ImportBuilder::new(connection, library_id);
+
+    // A full backup starts at the places root, which only holds the built-in containers
+    if json_data["root"].as_str() == Some("placesRoot") {
+        for container in firefox_children(json_data) {
+            let children = firefox_children(container);
+            if children.is_empty() {
+                continue;
+            }
+
+            let parent_id = match roots {
+                FirefoxRoots::Skip => None,
+                FirefoxRoots::Folders => {
+                    let name = firefox_root_name(container);
+                    let (created, updated) = firefox_dates(&mut builder.warnings, &name, container);
+                    Some(builder.add_folder(name, None, created, updated))
+                }
+            };
+            // Like the toolbar folder of an HTML import, its contents become favorites
+            let in_toolbar = container["root"].as_str() == Some("toolbarFolder");
+            for child in children {
+                walk_firefox_node(&mut builder, child, parent_id, in_toolbar);
+            }
+        }
+    } else {
+        walk_firefox_node(&mut builder, json_data, None, false);
+    }
+
+    builder.finish(connection)
+}
+
+fn walk_firefox_node(
+    builder: &mut ImportBuilder,
+    node: &Value,
+    parent_id: Option<i32>,
+    favorite: bool,
+) {
+    let title = node["title"].as_str().unwrap_or("").to_string();
+    let (created, updated) = firefox_dates(&mut builder.warnings, &title, node);
+
+    match node["type"].as_str() {
+        Some("text/x-moz-place-container") => {
+            let folder_id = builder.add_folder(title, parent_id, created, updated);
+            if favorite {
+                builder.favorite_folder_ids.push(folder_id);
+            }
+            for child in firefox_children(node) {
+                walk_firefox_node(builder, child, Some(folder_id), false);
+            }
+        }
+        Some("text/x-moz-place") => {
+            let Some(url) = node["uri"].as_str() else {
+                builder
+                    .invalid
+                    .push(format!("Bookmark \"{}\" has no URI", title));
+                return;
+            };
+            // Smart bookmarks are queries that only mean something inside Firefox
+            if url.starts_with("place:") {
+                return;
+            }
+
+            let name = if title.trim().is_empty() {
+                url.to_string()
+            } else {
+                title
+            };
+            let mut bookmark = ImportedBookmark::new(name, url.to_string(), parent_id, created);
+            bookmark.updated = updated;
+            bookmark.favicon_url = node["iconUri"].as_str().map(String::from);
+            bookmark.keyword = node["keyword"]
+                .as_str()
+                .filter(|keyword| !keyword.trim().is_empty())
+                .map(String::from);
+            bookmark.tags = node["tags"]
+                .as_str()
+                .map(|tags| {
+                    tags.split(',')
+                        .map(|tag| tag.trim().to_string())
+                        .filter(|tag| !tag.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default();
+            bookmark.description = node["annos"].as_array().and_then(|annos| {
+                annos
+                    .iter()
+                    .find(|anno| anno["name"] == "bookmarkProperties/description")
+                    .and_then(|anno| anno["value"].as_str())
+                    .filter(|description| !description.trim().is_empty())
+                    .map(String::from)
+            });
+
+            let bookmark_id = builder.add_bookmark(bookmark);
+            if favorite {
+                builder.favorite_bookmark_ids.push(bookmark_id);
+            }
+        }
+        // Separators have nothing to import
+        _ => {}
+    }
+}
+
+fn firefox_children(node: &Value) -> &[Value] {
+    node["children"].as_array().map_or(&[], |children| children)
+}
+
+/// `dateAdded` and `lastModified` are in microseconds
+fn firefox_dates(
+    warnings: &mut Vec<String>,
+    entry: &str,
+    node: &Value,
+) -> (OffsetDateTime, OffsetDateTime) {
+    let parse = |value: &str| {
+        value.parse::<i64>().ok().and_then(|microseconds| {
+            OffsetDateTime::from_unix_timestamp_nanos(i128::from(microseconds) * 1000).ok()
+        })
+    };
+    let date_added = match &node["dateAdded"] {
+        Value::Null => None,
+        value => Some(value.to_string()),
+    };
+    let created = checked_date(warnings, entry, date_added.as_deref(), parse);
+    let updated = match &node["lastModified"] {
+        Value::Null => None,
+        value => parse(&value.to_string()),
+    };
+    (created, updated.unwrap_or(created))
+}
+
+/// Firefox names its containers "menu", "toolbar" and so on, use the names it shows instead
+fn firefox_root_name(container: &Value) -> String {
+    match container["root"].as_str() {
+        Some("bookmarksMenuFolder") => "Bookmarks Menu".to_string(),
+        Some("toolbarFolder") => "Bookmarks Toolbar".to_string(),
+        Some("unfiledBookmarksFolder") => "Other Bookmarks".to_string(),
+        Some("mobileFolder") => "Mobile Bookmarks".to_string(),
+        _ => container["title"].as_str().unwrap_or("").to_string(),
+    }
+}
+
+#[derive(QueryableByName)]
+struct PlacesBookmark {
+    #[diesel(sql_type = Integer)]
+    id: i32,
+    /// 1 is a bookmark, 2 a folder and 3 a separator
+    #[diesel(sql_type = Integer)]
+    item_type: i32,
+    #[diesel(sql_type = Nullable<Integer>)]
+    parent: Option<i32>,
+    #[diesel(sql_type = Nullable<Text>)]
+    title: Option<String>,
+    #[diesel(sql_type = Nullable<BigInt>)]
+    date_added: Option<i64>,
+    #[diesel(sql_type = Nullable<BigInt>)]
+    last_modified: Option<i64>,
+    #[diesel(sql_type = Nullable<Text>)]
+    guid: Option<String>,
+    #[diesel(sql_type = Nullable<Text>)]
+    url: Option<String>,
+    #[diesel(sql_type = Nullable<Text>)]
+    keyword: Option<String>,
+}
+
+#[derive(QueryableByName)]
+struct PlacesTag {
+    #[diesel(sql_type = Integer)]
+    bookmark_id: i32,
+    #[diesel(sql_type = Text)]
+    tag: String,
+}
+
+const PLACES_TAGS_GUID: &str = "tags________";
+
+/// SQLite can only open files, so the upload is written to a temporary one and opened read-only.
+/// The bookmarks come back in the shape of a Firefox JSON backup.
+fn read_places_database(places_sqlite: &[u8]) -> Result<Value, String> {
+    let nanos = OffsetDateTime::now_utc().unix_timestamp_nanos();
+    let path = std::env::temp_dir().join(format!(
+        "nadamark-places-{}-{}.sqlite",
+        std::process::id(),
+        nanos
+    ));
+    fs::write(&path, places_sqlite).map_err(|e| format!("Failed to write temp file: {}", e))?;
+
+    let result = load_places(&path);
+    if let Err(e) = fs::remove_file(&path) {
+        eprintln!("Failed to remove temp file: {}", e);
+    }
+    result
+}
+
+fn load_places(path: &Path) -> Result<Value, String> {
+    // Immutable, because a copied profile may come without its -wal file and nothing else
+    // should see this file anyway
+    let url = format!("file:{}?mode=ro&immutable=1", path.display());
+    let mut places = SqliteConnection::establish(&url)
+        .map_err(|e| format!("Failed to open places.sqlite: {}", e))?;
+
+    let rows: Vec<PlacesBookmark> = sql_query(
+        "SELECT b.id, b.type AS item_type, b.parent, b.title, b.dateAdded AS date_added, \
+                b.lastModified AS last_modified, b.guid, p.url, \
+                (SELECT keyword FROM moz_keywords WHERE place_id = b.fk ORDER BY id LIMIT 1) \
+                    AS keyword \
+         FROM moz_bookmarks b \
+         LEFT JOIN moz_places p ON p.id = b.fk \
+         ORDER BY b.parent, b.position",
+    )
+    .load(&mut places)
+    .map_err(|e| format!("Failed to read moz_bookmarks: {}", e))?;
+
+    // Tags are folders below the tags root, holding one bookmark per tagged URL
+    let tag_rows: Vec<PlacesTag> = sql_query(
+        "SELECT b.id AS bookmark_id, t.title AS tag \
+         FROM moz_bookmarks b \
+         JOIN moz_bookmarks tagged ON tagged.fk = b.fk \
+         JOIN moz_bookmarks t ON t.id = tagged.parent \
+         JOIN moz_bookmarks tags_root ON tags_root.id = t.parent \
+         WHERE tags_root.guid = ? AND b.type = 1 AND t.title IS NOT NULL",
+    )
+    .bind::<Text, _>(PLACES_TAGS_GUID)
+    .load(&mut places)
+    .map_err(|e| format!("Failed to read tags: {}", e))?;
+
+    let mut tags: HashMap<i32, Vec<String>> = HashMap::new();
+    for tag_row in tag_rows {
+        tags.entry(tag_row.bookmark_id)
+            .or_default()
+            .push(tag_row.tag);
+    }
+    let mut children: HashMap<i32, Vec<&PlacesBookmark>> = HashMap::new();
+    for row in &rows {
+        if let Some(parent) = row.parent {
+            children.entry(parent).or_default().push(row);
+        }
+    }
+
+    let root = rows
+        .iter()
+        .find(|row| row.guid.as_deref() == Some("root________"))
+        .ok_or("places.sqlite has no root folder")?;
+    Ok(places_node(root, &children, &tags))
+}
+
+/// One `moz_bookmarks` row and everything below it, as it would appear in a JSON backup
+fn places_node(
+    row: &PlacesBookmark,
+    children: &HashMap<i32, Vec<&PlacesBookmark>>,
+    tags: &HashMap<i32, Vec<String>>,
+) -> Value {
+    let mut node = json!({
+        "title": row.title.as_deref().unwrap_or(""),
+        "dateAdded": row.date_added,
+        "lastModified": row.last_modified,
+    });
+
+    match row.item_type {
+        1 => {
+            node["type"] = json!("text/x-moz-place");
+            node["uri"] = json!(row.url);
+            node["keyword"] = json!(row.keyword);
+            if let Some(bookmark_tags) = tags.get(&row.id) {
+                node["tags"] = json!(bookmark_tags.join(","));
+            }
+        }
+        2 => {
+            node["type"] = json!("text/x-moz-place-container");
+            node["root"] = match row.guid.as_deref() {
+                Some("root________") => json!("placesRoot"),
+                Some("menu________") => json!("bookmarksMenuFolder"),
+                Some("toolbar_____") => json!("toolbarFolder"),
+                Some("unfiled_____") => json!("unfiledBookmarksFolder"),
+                Some("mobile______") => json!("mobileFolder"),
+                _ => Value::Null,
+            };
+            node["children"] = children
+                .get(&row.id)
+                .map(|rows| {
+                    rows.iter()
+                        // Tags were already attached to the bookmarks themselves
+                        .filter(|child| child.guid.as_deref() != Some(PLACES_TAGS_GUID))
+                        .map(|child| places_node(child, children, tags))
+                        .collect()
+                })
+                .unwrap_or_default();
+        }
+        _ => node["type"] = json!("text/x-moz-place-separator"),
+    }
+
+    node
+}
+
+/// Pinboard exports a flat list, `description` is the title and `extended` the notes
+fn parse_pinboard_json(
+    connection: &mut DbConnection,
+    library_id: i32,
+    folder_tags: &[String],
+    json_data: &Value,
+) -> ParsedBookmarks {
+    let mut builder = ImportBuilder::new(connection, library_id);
+    let mut tag_folders: HashMap<String, i32> = HashMap::new();
+
+    for post in json_data.as_array().map_or(&[][..], |posts| posts) {
+        let Some(url) = post["href"].as_str() else {
+            builder.invalid.push(format!(
+                "Post \"{}\" has no href",
+                post["description"].as_str().unwrap_or("")
+            ));
+            continue;
+        };
+        let tags: Vec<String> = post["tags"]
+            .as_str()
+            .unwrap_or("")
+            .split_whitespace()
+            .map(String::from)
+            .collect();
+        let created = parse_created_date(&mut builder.warnings, url, post["time"].as_str());
+
+        let folder_tag = folder_tags
+            .iter()
+            .find(|folder_tag| tags.iter().any(|tag| tag.eq_ignore_ascii_case(folder_tag)));
+        let folder_id = folder_tag.map(|folder_tag| {
+            *tag_folders
+                .entry(folder_tag.to_lowercase())
+                .or_insert_with(|| builder.add_folder(folder_tag.clone(), None, created, created))
+        });
+
+        let name = match post["description"].as_str() {
+            Some(title) if !title.trim().is_empty() => title.to_string(),
+            _ => url.to_string(),
+        };
+        let mut bookmark = ImportedBookmark::new(name, url.to_string(), folder_id, created);
+        bookmark.description = post["extended"]
+            .as_str()
+            .filter(|extended| !extended.trim().is_empty())
+            .map(String::from);
+        bookmark.tags = tags;
+        bookmark.read_later = post["toread"].as_str() == Some("yes");
+        builder.add_bookmark(bookmark);
+    }
+
+    builder.finish(connection)
+}
+
+#[derive(Deserialize)]
+struct PocketRow {
+    title: String,
+    url: String,
+    /// Unix seconds, read later so a bad one doesn't lose the row
+    time_added: Option<String>,
+    /// Separated by `|`
+    #[serde(default)]
+    tags: String,
+    /// "unread" or "archive"
+    #[serde(default)]
+    status: String,
+}
+
+/// Everything saved to Pocket was saved to read later, archived items count as read
+fn parse_pocket_csv(
+    connection: &mut DbConnection,
+    library_id: i32,
+    csv: &str,
+) -> Result<ParsedBookmarks, String> {
+    let mut builder = ImportBuilder::new(connection, library_id);
+
+    for row in ReaderBuilder::new()
+        .from_reader(csv.as_bytes())
+        .deserialize()
+    {
+        let row: PocketRow = match row {
+            Ok(row) => row,
+            Err(e) => {
+                builder.invalid.push(format!("Invalid Pocket row: {}", e));
+                continue;
+            }
+        };
+        let created = checked_date(
+            &mut builder.warnings,
+            &row.url,
+            row.time_added.as_deref(),
+            |seconds| unix_timestamp(seconds.parse().ok()),
+        );
+        let name = if row.title.trim().is_empty() {
+            row.url.clone()
+        } else {
+            row.title
+        };
+
+        let mut bookmark = ImportedBookmark::new(name, row.url, None, created);
+        bookmark.tags = row
+            .tags
+            .split('|')
+            .map(|tag| tag.trim().to_string())
+            .filter(|tag| !tag.is_empty())
+            .collect();
+        bookmark.read_later = true;
+        let bookmark_id = builder.add_bookmark(bookmark);
+        if row.status == "archive" {
+            builder.read_bookmark_ids.push(bookmark_id);
+        }
+    }
+
+    if builder.nothing_read() {
+        return Err(builder.invalid.join("\n"));
+    }
+    Ok(builder.finish(connection))
+}
+
+#[derive(Deserialize)]
+struct InstapaperRow {
+    #[serde(rename = "URL")]
+    url: String,
+    #[serde(rename = "Title")]
+    title: String,
+    #[serde(rename = "Selection", default)]
+    selection: String,
+    #[serde(rename = "Folder", default)]
+    folder: String,
+    #[serde(rename = "Timestamp")]
+    timestamp: Option<String>,
+}
+
+/// Instapaper's built-in folders are states rather than places: "Unread" and "Archive" are the
+/// read-later queue and "Starred" the favorites. Any other folder becomes a top-level folder.
+fn parse_instapaper_csv(
+    connection: &mut DbConnection,
+    library_id: i32,
+    csv: &str,
+) -> Result<ParsedBookmarks, String> {
+    let mut builder = ImportBuilder::new(connection, library_id);
+    let mut folder_ids: HashMap<String, i32> = HashMap::new();
+
+    for row in ReaderBuilder::new()
+        .from_reader(csv.as_bytes())
+        .deserialize()
+    {
+        let row: InstapaperRow = match row {
+            Ok(row) => row,
+            Err(e) => {
+                builder
+                    .invalid
+                    .push(format!("Invalid Instapaper row: {}", e));
+                continue;
+            }
+        };
+        let created = checked_date(
+            &mut builder.warnings,
+            &row.url,
+            row.timestamp.as_deref(),
+            |seconds| unix_timestamp(seconds.parse().ok()),
+        );
+        let name = if row.title.trim().is_empty() {
+            row.url.clone()
+        } else {
+            row.title
+        };
+
+        let folder = row.folder.trim();
+        let folder_id =
+            match folder {
+                "" | "Unread" | "Archive" | "Starred" => None,
+                _ => Some(*folder_ids.entry(folder.to_string()).or_insert_with(|| {
+                    builder.add_folder(folder.to_string(), None, created, created)
+                })),
+            };
+
+        let mut bookmark = ImportedBookmark::new(name, row.url, folder_id, created);
+        bookmark.description = Some(row.selection).filter(|selection| !selection.trim().is_empty());
+        bookmark.read_later = matches!(folder, "Unread" | "Archive");
+        let bookmark_id = builder.add_bookmark(bookmark);
+        match folder {
+            "Archive" => builder.read_bookmark_ids.push(bookmark_id),
+            "Starred" => builder.favorite_bookmark_ids.push(bookmark_id),
+            _ => {}
+        }
+    }
+
+    if builder.nothing_read() {
+        return Err(builder.invalid.join("\n"));
+    }
+    Ok(builder.finish(connection))
+}
+
+#[derive(Deserialize)]
+struct RaindropRow {
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    note: String,
+    #[serde(default)]
+    excerpt: String,
+    url: String,
+    /// Collection path like `Dev/Rust`
+    #[serde(default)]
+    folder: String,
+    /// Separated by `, `
+    #[serde(default)]
+    tags: String,
+    #[serde(default)]
+    created: String,
+    #[serde(default)]
+    favorite: String,
+}
+
+/// Raindrop's `Unsorted` collection is its root, every other path segment is a folder
+fn parse_raindrop_csv(
+    connection: &mut DbConnection,
+    library_id: i32,
+    csv: &str,
+) -> Result<ParsedBookmarks, String> {
+    let mut builder = ImportBuilder::new(connection, library_id);
+    let mut folder_ids: HashMap<String, i32> = HashMap::new();
+
+    for row in ReaderBuilder::new()
+        .from_reader(csv.as_bytes())
+        .deserialize()
+    {
+        let row: RaindropRow = match row {
+            Ok(row) => row,
+            Err(e) => {
+                builder.invalid.push(format!("Invalid Raindrop row: {}", e));
+                continue;
+            }
+        };
+        let created = parse_created_date(&mut builder.warnings, &row.url, Some(&row.created));
+
+        let mut folder_id = None;
+        let mut path = String::new();
+        for segment in row.folder.split('/').map(str::trim) {
+            if segment.is_empty() || (path.is_empty() && segment == "Unsorted") {
+                continue;
+            }
+            path.push('/');
+            path.push_str(segment);
+            folder_id = Some(*folder_ids.entry(path.clone()).or_insert_with(|| {
+                builder.add_folder(segment.to_string(), folder_id, created, created)
+            }));
+        }
+
+        let name = if row.title.trim().is_empty() {
+            row.url.clone()
+        } else {
+            row.title
+        };
+        let description = [row.excerpt.trim(), row.note.trim()]
+            .into_iter()
+            .filter(|text| !text.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n\n");
+
+        let mut bookmark = ImportedBookmark::new(name, row.url, folder_id, created);
+        bookmark.description = Some(description).filter(|description| !description.is_empty());
+        bookmark.tags = row
+            .tags
+            .split(',')
+            .map(|tag| tag.trim().to_string())
+            .filter(|tag| !tag.is_empty())
+            .collect();
+        let bookmark_id = builder.add_bookmark(bookmark);
+        if row.favorite.eq_ignore_ascii_case("true") {
+            builder.favorite_bookmark_ids.push(bookmark_id);
+        }
+    }
+
+    if builder.nothing_read() {
+        return Err(builder.invalid.join("\n"));
+    }
+    Ok(builder.finish(connection))
+}
+
+/// An open `<folder>`, only added once its title has been read or something goes inside it
+struct XbelFolder {
+    name: String,
+    created: OffsetDateTime,
+    updated: OffsetDateTime,
+    folder_id: Option<i32>,
+}
+
+#[derive(Clone, Copy)]
+enum XbelText {
+    Title,
+    Desc,
+}
+
+struct XbelParser {
+    builder: ImportBuilder,
+    found_root: bool,
+    open_folders: Vec<XbelFolder>,
+    open_bookmark: Option<ImportedBookmark>,
+    text: Option<XbelText>,
+}
+
+impl XbelParser {
+    fn start(&mut self, element: &BytesStart) {
+        match element.local_name().as_ref() {
+            b"xbel" => self.found_root = true,
+            b"folder" => {
+                add_open_folders(&mut self.builder, &mut self.open_folders);
+                let created = xbel_date(&mut self.builder.warnings, "Folder", element);
+                self.open_folders.push(XbelFolder {
+                    name: String::new(),
+                    created,
+                    updated: xbel_modified(element).unwrap_or(created),
+                    folder_id: None,
+                });
+            }
+            b"bookmark" => {
+                let href = xml_attribute(element, "href").unwrap_or_default();
+                let created = xbel_date(&mut self.builder.warnings, &href, element);
+                let mut bookmark = ImportedBookmark::new(String::new(), href, None, created);
+                bookmark.updated = xbel_modified(element).unwrap_or(created);
+                self.open_bookmark = Some(bookmark);
+            }
+            b"separator" => {
+                let parent_id = add_open_folders(&mut self.builder, &mut self.open_folders);
+                self.builder.add_separator(parent_id);
+            }
+            b"title" => self.text = Some(XbelText::Title),
+            b"desc" => self.text = Some(XbelText::Desc),
+            _ => {}
+        }
+    }
+
+    fn text(&mut self, content: &str) {
+        let Some(field) = self.text else {
+            return;
+        };
+        match (
+            self.open_bookmark.as_mut(),
+            self.open_folders.last_mut(),
+            field,
+        ) {
+            (Some(bookmark), _, XbelText::Title) => bookmark.name.push_str(content),
+            (Some(bookmark), _, XbelText::Desc) => bookmark
+                .description
+                .get_or_insert_with(String::new)
+                .push_str(content),
+            // Nadamark folders have no description
+            (None, Some(folder), XbelText::Title) => folder.name.push_str(content),
+            _ => {}
+        }
+    }
+
+    fn end(&mut self, name: &[u8]) {
+        match name {
+            b"title" | b"desc" => self.text = None,
+            b"folder" => {
+                add_open_folders(&mut self.builder, &mut self.open_folders);
+                self.open_folders.pop();
+            }
+            b"bookmark" => {
+                let Some(mut bookmark) = self.open_bookmark.take() else {
+                    return;
+                };
+                if bookmark.url.is_empty() {
+                    self.builder
+                        .invalid
+                        .push(format!("Bookmark \"{}\" has no href", bookmark.name.trim()));
+                    return;
+                }
+                bookmark.folder_id = add_open_folders(&mut self.builder, &mut self.open_folders);
+                bookmark.name = bookmark.name.trim().to_string();
+                if bookmark.name.is_empty() {
+                    bookmark.name = bookmark.url.clone();
+                }
+                bookmark.description = bookmark
+                    .description
+                    .map(|description| description.trim().to_string())
+                    .filter(|description| !description.is_empty());
+                self.builder.add_bookmark(bookmark);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn parse_xbel(
+    connection: &mut DbConnection,
+    library_id: i32,
+    xbel: &str,
+) -> Result<ParsedBookmarks, String> {
+    let mut parser = XbelParser {
+        builder: ImportBuilder::new(connection, library_id),
+        found_root: false,
+        open_folders: Vec::new(),
+        open_bookmark: None,
+        text: None,
+    };
+    let mut reader = Reader::from_str(xbel);
+
+    loop {
+        let event = reader
+            .read_event()
+            .map_err(|e| format!("Invalid XML at {}: {}", reader.error_position(), e))?;
+        match event {
+            Event::Start(element) => parser.start(&element),
+            Event::Empty(element) => {
+                parser.start(&element);
+                parser.end(element.local_name().as_ref());
+            }
+            Event::End(element) => parser.end(element.local_name().as_ref()),
+            Event::Text(content) => parser.text(&content.unescape().map_err(|e| e.to_string())?),
+            Event::CData(content) => parser.text(&String::from_utf8_lossy(&content)),
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    if !parser.found_root {
+        return Err("No <xbel> element".to_string());
+    }
+    if !parser.open_folders.is_empty() || parser.open_bookmark.is_some() {
+        return Err("Unexpected end of file".to_string());
+    }
+    Ok(parser.builder.finish(connection))
+}
+
+/// Adds the open folders that haven't been yet and returns the innermost one's id
+fn add_open_folders(builder: &mut ImportBuilder, open_folders: &mut [XbelFolder]) -> Option<i32> {
+    let mut parent_id = None;
+    for folder in open_folders.iter_mut() {
+        let folder_id = *folder.folder_id.get_or_insert_with(|| {
+            let name = folder.name.trim().to_string();
+            builder.add_folder(name, parent_id, folder.created, folder.updated)
+        });
+        parent_id = Some(folder_id);
+    }
+    parent_id
+}
+
+fn xml_attribute(element: &BytesStart, name: &str) -> Option<String> {
+    let attribute = element.try_get_attribute(name).ok()??;
+    attribute
+        .unescape_value()
+        .ok()
+        .map(|value| value.into_owned())
+}
+
+/// XBEL only asks for ISO 8601, but some tools write unix timestamps instead
+fn parse_xbel_date(value: &str) -> Option<OffsetDateTime> {
+    OffsetDateTime::parse(value, &Rfc3339)
+        .ok()
+        .or_else(|| unix_timestamp(value.parse().ok()))
+}
+
+fn xbel_modified(element: &BytesStart) -> Option<OffsetDateTime> {
+    xml_attribute(element, "modified").and_then(|modified| parse_xbel_date(&modified))
+}
+
+fn xbel_date(warnings: &mut Vec<String>, entry: &str, element: &BytesStart) -> OffsetDateTime {
+    checked_date(
+        warnings,
+        entry,
+        xml_attribute(element, "added").as_deref(),
+        parse_xbel_date,
+    )
+}
+
+/// Outlines with a link are bookmarks, anything else is a folder. Feed subscriptions count as
+/// links to the site, or to the feed when there's no site.
+fn parse_opml(
+    connection: &mut DbConnection,
+    library_id: i32,
+    opml: &str,
+) -> Result<ParsedBookmarks, String> {
+    let mut builder = ImportBuilder::new(connection, library_id);
+    let mut reader = Reader::from_str(opml);
+    let mut found_root = false;
+    // Where children of each open outline go, links can't hold anything so theirs go up a level
+    let mut parents: Vec<Option<i32>> = Vec::new();
+
+    loop {
+        let event = reader
+            .read_event()
+            .map_err(|e| format!("Invalid XML at {}: {}", reader.error_position(), e))?;
+        let (element, has_children) = match &event {
+            Event::Start(element) => (element, true),
+            Event::Empty(element) => (element, false),
+            Event::End(element) => {
+                if element.local_name().as_ref() == b"outline" {
+                    parents.pop();
+                }
+                continue;
+            }
+            Event::Eof => break,
+            _ => continue,
+        };
+
+        match element.local_name().as_ref() {
+            b"opml" => found_root = true,
+            b"outline" => {
+                let parent_id = parents.last().copied().flatten();
+                let name = xml_attribute(element, "text")
+                    .or_else(|| xml_attribute(element, "title"))
+                    .unwrap_or_default();
+                let created = checked_date(
+                    &mut builder.warnings,
+                    &name,
+                    xml_attribute(element, "created").as_deref(),
+                    |created| {
+                        OffsetDateTime::parse(created, &Rfc2822)
+                            .or_else(|_| OffsetDateTime::parse(created, &Rfc3339))
+                            .ok()
+                    },
+                );
+                let url = ["url", "htmlUrl", "xmlUrl"]
+                    .into_iter()
+                    .find_map(|attribute| xml_attribute(element, attribute))
+                    .filter(|url| !url.trim().is_empty());
+
+                let children_parent = match url {
+                    Some(url) => {
+                        let name = if name.trim().is_empty() {
+                            url.clone()
+                        } else {
+                            name
+                        };
+                        let mut bookmark = ImportedBookmark::new(name, url, parent_id, created);
+                        bookmark.description = xml_attribute(element, "description")
+                            .filter(|description| !description.trim().is_empty());
+                        // OPML categories are comma-separated and may be paths like `/a/b`
+                        bookmark.tags = xml_attribute(element, "category")
+                            .unwrap_or_default()
+                            .split(',')
+                            .map(|tag| tag.trim().trim_start_matches('/').to_string())
+                            .filter(|tag| !tag.is_empty())
+                            .collect();
+                        builder.add_bookmark(bookmark);
+                        parent_id
+                    }
+                    None => Some(builder.add_folder(name, parent_id, created, created)),
+                };
+                if has_children {
+                    parents.push(children_parent);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if !found_root {
+        return Err("No <opml> element".to_string());
+    }
+    if !parents.is_empty() {
+        return Err("Unexpected end of file".to_string());
+    }
+    Ok(builder.finish(connection))
+}
+
+const SAFARI_BOOKMARKS_BAR: &str = "BookmarksBar";
+const SAFARI_BOOKMARKS_MENU: &str = "BookmarksMenu";
+const SAFARI_READING_LIST: &str = "com.apple.ReadingList";
+
+fn parse_safari_plist(
+    connection: &mut DbConnection,
+    library_id: i32,
+    bookmarks_bar: BookmarkBar,
+    reading_list: ReadingList,
+    plist_value: &plist::Value,
+) -> ParsedBookmarks {
+    let mut builder = ImportBuilder::new(connection, library_id);
+
+    for child in safari_children(plist_value) {
+        match safari_title(child) {
+            SAFARI_BOOKMARKS_BAR => match bookmarks_bar {
+                BookmarkBar::Favorites => {
+                    for bar_child in safari_children(child) {
+                        walk_safari_node(&mut builder, bar_child, None, true);
+                    }
+                }
+                BookmarkBar::Folder => {
+                    if !safari_children(child).is_empty() {
+                        walk_safari_node(&mut builder, child, None, false);
+                    }
+                }
+            },
+            // The menu is where everything outside the bar lives, so it is the top level here
+            SAFARI_BOOKMARKS_MENU => {
+                for menu_child in safari_children(child) {
+                    walk_safari_node(&mut builder, menu_child, None, false);
+                }
+            }
+            SAFARI_READING_LIST => match reading_list {
+                ReadingList::ReadLater => {
+                    for item in safari_children(child) {
+                        add_reading_list_item(&mut builder, item);
+                    }
+                }
+                ReadingList::Folder => {
+                    if !safari_children(child).is_empty() {
+                        let now = OffsetDateTime::now_local().unwrap_or(OffsetDateTime::now_utc());
+                        let folder_id =
+                            builder.add_folder("Reading List".to_string(), None, now, now);
+                        for item in safari_children(child) {
+                            walk_safari_node(&mut builder, item, Some(folder_id), false);
+                        }
+                    }
+                }
+            },
+            _ => walk_safari_node(&mut builder, child, None, false),
+        }
+    }
+
+    builder.finish(connection)
+}
+
+/// Safari keeps no dates on folders or regular bookmarks, only on Reading List items
+fn walk_safari_node(
+    builder: &mut ImportBuilder,
+    node: &plist::Value,
+    parent_id: Option<i32>,
+    favorite: bool,
+) {
+    let now = OffsetDateTime::now_local().unwrap_or(OffsetDateTime::now_utc());
+    let Some(dict) = node.as_dictionary() else {
+        return;
+    };
+
+    // Proxies are the History and Bonjour entries, which aren't bookmarks
+    match dict.get("WebBookmarkType").and_then(|t| t.as_string()) {
+        Some("WebBookmarkTypeList") => {
+            // Safari shows its bar as "Favorites"
+            let name = match safari_title(node) {
+                SAFARI_BOOKMARKS_BAR => "Favorites",
+                title => title,
+            };
+            let folder_id = builder.add_folder(name.to_string(), parent_id, now, now);
+            if favorite {
+                builder.favorite_folder_ids.push(folder_id);
+            }
+            for child in safari_children(node) {
+                walk_safari_node(builder, child, Some(folder_id), false);
+            }
+        }
+        Some("WebBookmarkTypeLeaf") => {
+            let Some(bookmark) = safari_bookmark(node, parent_id) else {
+                builder
+                    .invalid
+                    .push("A bookmark has no URLString".to_string());
+                return;
+            };
+            let bookmark_id = builder.add_bookmark(bookmark);
+            if favorite {
+                builder.favorite_bookmark_ids.push(bookmark_id);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Items that were opened since they were added count as read
+fn add_reading_list_item(builder: &mut ImportBuilder, node: &plist::Value) {
+    let Some(mut bookmark) = safari_bookmark(node, None) else {
+        builder
+            .invalid
+            .push("A Reading List item has no URLString".to_string());
+        return;
+    };
+    let reading_list = safari_reading_list(node);
+    bookmark.description = reading_list
+        .and_then(|item| item.get("PreviewText"))
+        .and_then(|text| text.as_string())
+        .filter(|text| !text.trim().is_empty())
+        .map(String::from);
+    bookmark.read_later = true;
+    let viewed = reading_list.is_some_and(|item| item.contains_key("DateLastViewed"));
+
+    let bookmark_id = builder.add_bookmark(bookmark);
+    if viewed {
+        builder.read_bookmark_ids.push(bookmark_id);
+    }
+}
+
+/// Leaves keep their title in `URIDictionary`, lists have their own `Title`
+fn safari_bookmark(node: &plist::Value, parent_id: Option<i32>) -> Option<ImportedBookmark> {
+    let created = safari_reading_list(node)
+        .and_then(|item| safari_date(item, "DateAdded"))
+        .unwrap_or(OffsetDateTime::now_local().unwrap_or(OffsetDateTime::now_utc()));
+    let dict = node.as_dictionary()?;
+    let url = dict.get("URLString")?.as_string()?;
+    let name = dict
+        .get("URIDictionary")
+        .and_then(|uri| uri.as_dictionary())
+        .and_then(|uri| uri.get("title"))
+        .and_then(|title| title.as_string())
+        .filter(|title| !title.trim().is_empty())
+        .unwrap_or(url);
+    Some(ImportedBookmark::new(
+        name.to_string(),
+        url.to_string(),
+        parent_id,
+        created,
+    ))
+}
+
+fn safari_title(node: &plist::Value) -> &str {
+    node.as_dictionary()
+        .and_then(|dict| dict.get("Title"))
+        .and_then(|title| title.as_string())
+        .unwrap_or("")
+}
+
+fn safari_children(node: &plist::Value) -> &[plist::Value] {
+    node.as_dictionary()
+        .and_then(|dict| dict.get("Children"))
+        .and_then(|children| children.as_array())
+        .map_or(&[], |children| children)
+}
+
+fn safari_reading_list(node: &plist::Value) -> Option<&plist::Dictionary> {
+    node.as_dictionary()?.get("ReadingList")?.as_dictionary()
+}
+
+fn safari_date(dict: &plist::Dictionary, key: &str) -> Option<OffsetDateTime> {
+    let date = dict.get(key)?.as_date()?;
+    Some(OffsetDateTime::from(SystemTime::from(date)))
+}
+
+fn unix_timestamp(seconds: Option<i64>) -> Option<OffsetDateTime> {
+    seconds.and_then(|seconds| OffsetDateTime::from_unix_timestamp(seconds).ok())
+}
+
+/// Microseconds between 1601-01-01, where WebKit timestamps start, and the unix epoch
+const WEBKIT_EPOCH_OFFSET_MICROSECONDS: i64 = 11_644_473_600_000_000;
+
+fn parse_chromium_json(
+    connection: &mut DbConnection,
+    library_id: i32,
+    bookmark_bar: BookmarkBar,
+    json_data: &Value,
+) -> ParsedBookmarks {
+    let mut builder = ImportBuilder::new(connection, library_id);
+    let roots = &json_data["roots"];
+
+    let bar = &roots["bookmark_bar"];
+    match bookmark_bar {
+        BookmarkBar::Favorites => {
+            for child in chromium_children(bar) {
+                walk_chromium_node(&mut builder, child, None, true);
+            }
+        }
+        BookmarkBar::Folder => {
+            if !chromium_children(bar).is_empty() {
+                walk_chromium_node(&mut builder, bar, None, false);
+            }
+        }
+    }
+
+    // "Other bookmarks" is where everything outside the bar lives, so it is the top level here
+    for child in chromium_children(&roots["other"]) {
+        walk_chromium_node(&mut builder, child, None, false);
+    }
+
+    // Bookmarks from phones keep their own folder
+    let synced = &roots["synced"];
+    if !chromium_children(synced).is_empty() {
+        walk_chromium_node(&mut builder, synced, None, false);
+    }
+
+    builder.finish(connection)
+}
+
+fn walk_chromium_node(
+    builder: &mut ImportBuilder,
+    node: &Value,
+    parent_id: Option<i32>,
+    favorite: bool,
+) {
+    let name = node["name"].as_str().unwrap_or("").to_string();
+    let created = checked_date(
+        &mut builder.warnings,
+        &name,
+        node["date_added"].as_str().filter(|time| *time != "0"),
+        webkit_timestamp,
+    );
+
+    match node["type"].as_str() {
+        Some("folder") => {
+            let updated = node["date_modified"]
+                .as_str()
+                .and_then(webkit_timestamp)
+                .unwrap_or(created);
+            let folder_id = builder.add_folder(name, parent_id, created, updated);
+            if favorite {
+                builder.favorite_folder_ids.push(folder_id);
+            }
+            for child in chromium_children(node) {
+                walk_chromium_node(builder, child, Some(folder_id), false);
+            }
+        }
+        Some("url") => {
+            let Some(url) = node["url"].as_str() else {
+                builder
+                    .invalid
+                    .push(format!("Bookmark \"{}\" has no URL", name));
+                return;
+            };
+            let name = if name.trim().is_empty() {
+                url.to_string()
+            } else {
+                name
+            };
+            let bookmark_id = builder.add_bookmark(ImportedBookmark::new(
+                name,
+                url.to_string(),
+                parent_id,
+                created,
+            ));
+            if favorite {
+                builder.favorite_bookmark_ids.push(bookmark_id);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn chromium_children(node: &Value) -> &[Value] {
+    node["children"].as_array().map_or(&[], |children| children)
+}
+
+/// Chromium stores times as strings of microseconds since 1601, with "0" for unknown
+fn webkit_timestamp(value: &str) -> Option<OffsetDateTime> {
+    let microseconds = value.parse::<i64>().ok().filter(|time| *time > 0)?;
+    OffsetDateTime::from_unix_timestamp_nanos(
+        i128::from(microseconds - WEBKIT_EPOCH_OFFSET_MICROSECONDS) * 1000,
+    )
+    .ok()
+}
+
+/// Browsers write `LAST_MODIFIED` as a unix timestamp, like `ADD_DATE`
+fn parse_add_date(warnings: &mut Vec<String>, entry: &str, element: &ElementRef) -> OffsetDateTime {
+    checked_date(
+        warnings,
+        entry,
+        element.value().attr("add_date"),
+        |timestamp| unix_timestamp(timestamp.parse().ok()),
+    )
+}
+
+fn parse_last_modified(element: &ElementRef) -> Option<OffsetDateTime> {
+    element
+        .value()
+        .attr("last_modified")
+        .and_then(|timestamp| timestamp.parse::<i64>().ok())
+        .and_then(|unix_timestamp| OffsetDateTime::from_unix_timestamp(unix_timestamp).ok())
+}
+
+fn parse_created_date(
+    warnings: &mut Vec<String>,
+    entry: &str,
+    date_str: Option<&str>,
+) -> OffsetDateTime {
+    checked_date(warnings, entry, date_str, |date| {
+        OffsetDateTime::parse(date, &Rfc3339).ok()
+    })
+}
+
+/// Entries without a date get the time of the import, which is only worth a warning when the
+/// file had a date that couldn't be read
+fn checked_date(
+    warnings: &mut Vec<String>,
+    entry: &str,
+    value: Option<&str>,
+    parse: impl FnOnce(&str) -> Option<OffsetDateTime>,
+) -> OffsetDateTime {
+    let now = OffsetDateTime::now_local().unwrap_or(OffsetDateTime::now_utc());
+    let Some(value) = value.map(str::trim).filter(|value| !value.is_empty()) else {
+        return now;
+    };
+    parse(value).unwrap_or_else(|| {
+        warnings.push(format!(
+            "\"{}\" has a date that couldn't be read ({}), using the time of the import",
+            entry, value
+        ));
+        now
+    })
+}
+
+/// Anything without a scheme, or with spaces in it, won't open from a browser
+fn url_warning(name: &str, url: &str) -> Option<String> {
+    let valid = url.split_once(':').is_some_and(|(scheme, rest)| {
+        scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+            && scheme
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+            && !rest.is_empty()
+    }) && !url.trim().contains(char::is_whitespace);
+    (!valid).then(|| format!("\"{}\" has an invalid URL: {}", name, url))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        export,
+        models::{NewBookmarkRequest, DEFAULT_LIBRARY_ID},
+    };
+    use diesel::{connection::SimpleConnection, ExpressionMethods};
+
+    fn add_bookmark(connection: &mut DbConnection, bookmark: NewBookmarkRequest) {
+        database::create_new_bookmark(connection, bookmark).unwrap();
+    }
+
+    fn new_bookmark(name: &str, url: &str) -> NewBookmarkRequest {
+        NewBookmarkRequest {
+            library_id: DEFAULT_LIBRARY_ID,
+            name: name.to_string(),
+            url: url.to_string(),
+            folder_id: None,
+            tags: Vec::new(),
+            description: None,
+            read_later: false,
+        }
+    }
+
+    /// Exports the default library and reads the file back in
+    fn html_round_trip(connection: &mut DbConnection) -> ParsedBookmarks {
+        let html = export::bookmarks_html(connection, DEFAULT_LIBRARY_ID);
+        parse_bookmarks_html(connection, DEFAULT_LIBRARY_ID, &html).unwrap()
+    }
+
+    /// A small browser export: a bookmark at the top and a folder with a subfolder
+    const SAMPLE_HTML: &str = r#"<!DOCTYPE NETSCAPE-Bookmark-file-1>
+<DL><p>
+    <DT><H3>Dev</H3>
+    <DL><p>
+        <DT><A HREF="https://rust-lang.org">Rust</A>
+        <DT><H3>Docs</H3>
+        <DL><p>
+            <DT><A HREF="https://docs.rs">Docs.rs</A>
+        </DL><p>
+    </DL><p>
+    <DT><A HREF="https://example.com">Example</A>
+</DL><p>
+"#;
+
+    fn import_query(mode: ImportMode) -> ImportQuery {
+        ImportQuery {
+            library_id: DEFAULT_LIBRARY_ID,
+            mode,
+            dry_run: false,
+            folder_id: None,
+            wrap: false,
+        }
+    }
+
+    /// Imports a Netscape file like `/api/import-html` does
+    fn import_html(connection: &mut DbConnection, query: &ImportQuery, html: &str) -> StatusCode {
+        let parsed = parse_bookmarks_html(connection, query.library_id, html).unwrap();
+        save_import(connection, query, "HTML", parsed).status()
+    }
+
+    fn library_counts(connection: &mut DbConnection) -> (usize, usize) {
+        let folders = database::get_all_folders(connection, DEFAULT_LIBRARY_ID).unwrap();
+        let bookmarks = database::get_all_bookmarks(connection, DEFAULT_LIBRARY_ID).unwrap();
+        (folders.len(), bookmarks.len())
+    }
+
+    #[test]
+    fn html_round_trip_keeps_markup_in_descriptions() {
+        let pool = database::test_pool();
+        let mut connection = pool.get().unwrap();
+        add_bookmark(
+            &mut connection,
+            NewBookmarkRequest {
+                description: Some("Use <v2> & not v1".to_string()),
+                ..new_bookmark("Docs <beta> & co", "https://docs.com")
+            },
+        );
+
+        let parsed = html_round_trip(&mut connection);
+        assert_eq!(parsed.bookmarks[0].name, "Docs <beta> & co");
+        assert_eq!(
+            parsed.bookmarks[0].description.as_deref(),
+            Some("Use <v2> & not v1")
+        );
+    }
+
+    #[test]
+    fn html_descriptions_keep_text_inside_markup() {
+        let pool = database::test_pool();
+        let mut connection = pool.get().unwrap();
+        let html = r#"<!DOCTYPE NETSCAPE-Bookmark-file-1>
+<DL><p>
+    <DT><H3>Folder</H3>
+    <DD>About <i>this</i> folder
+    <DL><p>
+        <DT><A HREF="https://api.com">API</A>
+        <DD>Use <b>v2</b> &amp; not v1
+    </DL><p>
+</DL><p>
+"#;
+
+        let parsed = parse_bookmarks_html(&mut connection, DEFAULT_LIBRARY_ID, html).unwrap();
+        assert_eq!(parsed.folders.len(), 1);
+        let bookmark = &parsed.bookmarks[0];
+        assert_eq!(bookmark.folder_id, Some(parsed.folders[0].id));
+        assert_eq!(bookmark.description.as_deref(), Some("Use v2 & not v1"));
+    }
+
+    #[test]
+    fn html_toolbar_folder_holds_the_favorites_in_their_order() {
+        let pool = database::test_pool();
+        let mut connection = pool.get().unwrap();
+        let work_id =
+            database::create_new_folder(&mut connection, DEFAULT_LIBRARY_ID, "Work".into(), None)
+                .unwrap();
+        let news_id =
+            database::create_new_folder(&mut connection, DEFAULT_LIBRARY_ID, "News".into(), None)
+                .unwrap();
+        let docs_id = database::create_new_bookmark(
+            &mut connection,
+            NewBookmarkRequest {
+                folder_id: Some(work_id),
+                ..new_bookmark("Docs", "https://docs.com")
+            },
+        )
+        .unwrap();
+        let hacker_news_id = database::create_new_bookmark(
+            &mut connection,
+            NewBookmarkRequest {
+                folder_id: Some(news_id),
+                ..new_bookmark("Hacker News", "https://news.ycombinator.com")
+            },
+        )
+        .unwrap();
+        database::toggle_bookmark_favorite(&mut connection, docs_id).unwrap();
+        database::toggle_folder_favorite(&mut connection, news_id).unwrap();
+        database::toggle_bookmark_favorite(&mut connection, hacker_news_id).unwrap();
+
+        let html = export::bookmarks_html(&mut connection, DEFAULT_LIBRARY_ID);
+        assert_eq!(html.matches("PERSONAL_TOOLBAR_FOLDER").count(), 1);
+        assert_eq!(html.matches(">Docs</A>").count(), 1);
+        assert_eq!(html.matches(">Hacker News</A>").count(), 1);
+        assert!(html.find(">Docs</A>") < html.find(">News</H3>"));
+
+        let parsed = parse_bookmarks_html(&mut connection, DEFAULT_LIBRARY_ID, &html).unwrap();
+        let toolbar = parsed
+            .folders
+            .iter()
+            .find(|folder| folder.name == "Bookmarks Toolbar")
+            .unwrap();
+        let news = parsed.folders.iter().find(|folder| folder.name == "News");
+        assert_eq!(news.unwrap().parent_id, Some(toolbar.id));
+        assert!(news.unwrap().favorite);
+        let docs = parsed
+            .bookmarks
+            .iter()
+            .find(|bookmark| bookmark.name == "Docs");
+        assert_eq!(docs.unwrap().folder_id, Some(toolbar.id));
+        assert!(docs.unwrap().favorite);
+        // Hacker News comes along inside its favorite folder
+        let hacker_news = parsed
+            .bookmarks
+            .iter()
+            .find(|bookmark| bookmark.name == "Hacker News");
+        assert_eq!(hacker_news.unwrap().folder_id, Some(news.unwrap().id));
+    }
+
+    #[test]
+    fn html_folders_with_the_same_name_keep_their_own_children() {
+        let pool = database::test_pool();
+        let mut connection = pool.get().unwrap();
+        // Browsers leave <p> and <DT> open
+        let html = r#"<!DOCTYPE NETSCAPE-Bookmark-file-1>
+<DL><p>
+    <DT><H3>ProjectA</H3>
+    <DL><p>
+        <DT><H3>Docs</H3>
+        <DL><p>
+            <DT><A HREF="https://a.com">A docs</A>
+        </DL><p>
+    </DL><p>
+    <DT><H3>ProjectB</H3>
+    <DL><p>
+        <DT><H3>Docs</H3>
+        <DD>Also docs
+        <DL><p>
+            <DT><A HREF="https://b.com">B docs</A>
+        </DL><p>
+    </DL><p>
+</DL><p>
+"#;
+
+        let parsed = parse_bookmarks_html(&mut connection, DEFAULT_LIBRARY_ID, html).unwrap();
+        let folder_named = |name: &str| {
+            parsed
+                .folders
+                .iter()
+                .find(|folder| folder.name == name)
+                .unwrap()
+        };
+        let docs_parent = |bookmark_name: &str| {
+            let bookmark = parsed
+                .bookmarks
+                .iter()
+                .find(|bookmark| bookmark.name == bookmark_name)
+                .unwrap();
+            let docs = parsed
+                .folders
+                .iter()
+                .find(|folder| Some(folder.id) == bookmark.folder_id)
+                .unwrap();
+            assert_eq!(docs.name, "Docs");
+            docs.parent_id
+        };
+        assert_eq!(parsed.folders.len(), 4);
+        assert_eq!(docs_parent("A docs"), Some(folder_named("ProjectA").id));
+        assert_eq!(docs_parent("B docs"), Some(folder_named("ProjectB").id));
+    }
+
+    #[test]
+    fn html_round_trip_keeps_quotes_in_attributes() {
+        use crate::schema::bookmarks;
+        let pool = database::test_pool();
+        let mut connection = pool.get().unwrap();
+        add_bookmark(
+            &mut connection,
+            NewBookmarkRequest {
+                tags: vec!["a\"b".to_string(), "c&d<e".to_string()],
+                ..new_bookmark("Search", "https://search.com/?q=a&b=\"c\"")
+            },
+        );
+        diesel::update(bookmarks::table)
+            .set(bookmarks::keyword.eq("k\"w"))
+            .execute(&mut connection)
+            .unwrap();
+
+        let parsed = html_round_trip(&mut connection);
+        let bookmark = &parsed.bookmarks[0];
+        assert_eq!(bookmark.url, "https://search.com/?q=a&b=\"c\"");
+        assert_eq!(bookmark.keyword.as_deref(), Some("k\"w"));
+        let mut tags = parsed.tags[&bookmark.id].clone();
+        tags.sort();
+        assert_eq!(tags, vec!["a\"b", "c&d<e"]);
+    }
+
+    #[test]
+    fn xbel_round_trip_keeps_added_and_modified_times() {
+        use crate::schema::{bookmarks, folders};
+        let pool = database::test_pool();
+        let mut connection = pool.get().unwrap();
+        let added = OffsetDateTime::from_unix_timestamp(1_600_000_000).unwrap();
+        let modified = OffsetDateTime::from_unix_timestamp(1_700_000_000).unwrap();
+        let folder_id = database::create_new_folder(
+            &mut connection,
+            DEFAULT_LIBRARY_ID,
+            "Dev".to_string(),
+            None,
+        )
+        .unwrap();
+        add_bookmark(
+            &mut connection,
+            NewBookmarkRequest {
+                folder_id: Some(folder_id),
+                description: Some("The <Rust> site".to_string()),
+                ..new_bookmark("Rust", "https://rust-lang.org")
+            },
+        );
+        diesel::update(folders::table)
+            .set((folders::created.eq(added), folders::updated.eq(modified)))
+            .execute(&mut connection)
+            .unwrap();
+        diesel::update(bookmarks::table)
+            .set((
+                bookmarks::created.eq(added),
+                bookmarks::updated.eq(modified),
+            ))
+            .execute(&mut connection)
+            .unwrap();
+
+        let xbel = export::bookmarks_xbel(&mut connection, DEFAULT_LIBRARY_ID);
+        let parsed = parse_xbel(&mut connection, DEFAULT_LIBRARY_ID, &xbel).unwrap();
+        let folder = &parsed.folders[0];
+        assert_eq!(folder.name, "Dev");
+        assert_eq!((folder.created, folder.updated), (added, modified));
+        let bookmark = &parsed.bookmarks[0];
+        assert_eq!(bookmark.folder_id, Some(folder.id));
+        assert_eq!((bookmark.created, bookmark.updated), (added, modified));
+        assert_eq!(bookmark.description.as_deref(), Some("The <Rust> site"));
+    }
+
+    #[test]
+    fn xbel_separators_stay_in_place_when_bookmarks_move() {
+        let pool = database::test_pool();
+        let mut connection = pool.get().unwrap();
+        let xbel = r#"<?xml version="1.0" encoding="UTF-8"?>
+<xbel version="1.0">
+    <folder>
+        <title>Dev</title>
+        <bookmark href="https://a.com"><title>A</title></bookmark>
+        <separator/>
+        <bookmark href="https://b.com"><title>B</title></bookmark>
+        <separator/>
+        <bookmark href="https://c.com"><title>C</title></bookmark>
+    </folder>
+</xbel>
+"#;
+        let parsed = parse_xbel(&mut connection, DEFAULT_LIBRARY_ID, xbel).unwrap();
+        database::insert_import(
+            &mut connection,
+            parsed.folders,
+            parsed.bookmarks,
+            parsed.tags,
+            parsed.separators,
+        )
+        .unwrap();
+        let folder_id =
+            database::get_all_folders(&mut connection, DEFAULT_LIBRARY_ID).unwrap()[0].id;
+        let children = database::get_all_child_bookmarks(
+            &mut connection,
+            DEFAULT_LIBRARY_ID,
+            &Some(folder_id),
+        )
+        .unwrap();
+        // A goes to the end, the separators stay before B and C
+        database::change_bookmark_folder(
+            &mut connection,
+            children[0].id,
+            None,
+            Some(folder_id),
+            Some(2),
+        )
+        .unwrap();
+
+        let xbel = export::bookmarks_xbel(&mut connection, DEFAULT_LIBRARY_ID);
+        let parsed = parse_xbel(&mut connection, DEFAULT_LIBRARY_ID, &xbel).unwrap();
+        let mut entries: Vec<(i32, &str)> = parsed
+            .bookmarks
+            .iter()
+            .map(|bookmark| (bookmark.position, bookmark.name.as_str()))
+            .chain(
+                parsed
+                    .separators
+                    .iter()
+                    .map(|separator| (separator.position, "-")),
+            )
+            .collect();
+        entries.sort();
+        assert_eq!(
+            entries.iter().map(|(_, name)| *name).collect::<Vec<_>>(),
+            vec!["-", "B", "-", "C", "A"]
+        );
+    }
+
+    #[test]
+    fn places_bookmarks_with_several_keywords_are_read_once() {
+        let path = std::env::temp_dir().join(format!(
+            "nadamark-test-places-{}.sqlite",
+            std::process::id()
+        ));
+        let _ = fs::remove_file(&path);
+        let mut places = SqliteConnection::establish(&path.to_string_lossy()).unwrap();
+        places
+            .batch_execute(
+                "CREATE TABLE moz_places (id INTEGER PRIMARY KEY, url TEXT);
+                CREATE TABLE moz_keywords (id INTEGER PRIMARY KEY, keyword TEXT, place_id INTEGER);
+                CREATE TABLE moz_bookmarks (id INTEGER PRIMARY KEY, type INTEGER, fk INTEGER,
+                    parent INTEGER, position INTEGER, title TEXT, dateAdded INTEGER,
+                    lastModified INTEGER, guid TEXT);
+                INSERT INTO moz_places VALUES (1, 'https://search.com/?q=%s');
+                INSERT INTO moz_keywords VALUES (1, 's', 1), (2, 'search', 1);
+                INSERT INTO moz_bookmarks VALUES
+                    (1, 2, NULL, 0, 0, '', 0, 0, 'root________'),
+                    (2, 2, NULL, 1, 0, 'menu', 0, 0, 'menu________'),
+                    (3, 1, 1, 2, 0, 'Search', 0, 0, 'bookmark0001');",
+            )
+            .unwrap();
+        drop(places);
+
+        let root = load_places(&path);
+        fs::remove_file(&path).unwrap();
+        let root = root.unwrap();
+        let menu = &firefox_children(&root)[0];
+        let bookmarks = firefox_children(menu);
+        assert_eq!(bookmarks.len(), 1);
+        assert_eq!(bookmarks[0]["keyword"], "s");
+    }
+
+    const SAFARI_BINARY: &[u8] = include_bytes!("../tests/fixtures/safari/Bookmarks.plist");
+    const SAFARI_XML: &[u8] = include_bytes!("../tests/fixtures/safari/Bookmarks.xml.plist");
+
+    fn parse_safari_fixture(
+        fixture: &[u8],
+        bookmarks_bar: BookmarkBar,
+        reading_list: ReadingList,
+    ) -> ParsedBookmarks {
+        let pool = database::test_pool();
+        let mut connection = pool.get().unwrap();
+        let plist_value = plist::Value::from_reader(Cursor::new(fixture)).unwrap();
+        parse_safari_plist(
+            &mut connection,
+            DEFAULT_LIBRARY_ID,
+            bookmarks_bar,
+            reading_list,
+            &plist_value,
+        )
+    }
+
+    /// Every folder and bookmark as a path like `Dev/Docs/docs.rs`
+    fn paths(parsed: &ParsedBookmarks) -> Vec<String> {
+        let folder_path = |folder_id: Option<i32>| {
+            let mut names = Vec::new();
+            let mut current = folder_id;
+            while let Some(folder) =
+                current.and_then(|id| parsed.folders.iter().find(|folder| folder.id == id))
+            {
+                names.insert(0, folder.name.clone());
+                current = folder.parent_id;
+            }
+            names
+        };
+        let mut paths: Vec<String> = parsed
+            .folders
+            .iter()
+            .map(|folder| folder_path(Some(folder.id)).join("/") + "/")
+            .chain(parsed.bookmarks.iter().map(|bookmark| {
+                let mut names = folder_path(bookmark.folder_id);
+                names.push(bookmark.name.clone());
+                names.join("/")
+            }))
+            .collect();
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn safari_binary_and_xml_plists_give_the_same_tree() {
+        let binary = parse_safari_fixture(
+            SAFARI_BINARY,
+            BookmarkBar::Favorites,
+            ReadingList::ReadLater,
+        );
+        let xml = parse_safari_fixture(SAFARI_XML, BookmarkBar::Favorites, ReadingList::ReadLater);
+
+        assert_eq!(
+            paths(&binary),
+            vec![
+                "Already read",
+                "Apple",
+                "Dev/",
+                "Dev/Docs/",
+                "Dev/Docs/docs.rs",
+                "Dev/Rust",
+                "Long read",
+                "News/",
+                "News/Hacker News",
+                "Wikipedia",
+            ]
+        );
+        assert_eq!(paths(&binary), paths(&xml));
+        assert!(binary.invalid.is_empty());
+    }
+
+    #[test]
+    fn safari_bookmarks_bar_becomes_favorites_or_a_folder() {
+        let favorites = parse_safari_fixture(
+            SAFARI_BINARY,
+            BookmarkBar::Favorites,
+            ReadingList::ReadLater,
+        );
+        let apple = favorites
+            .bookmarks
+            .iter()
+            .find(|bookmark| bookmark.name == "Apple")
+            .unwrap();
+        assert!(apple.favorite);
+        assert_eq!(apple.folder_id, None);
+        let news = favorites
+            .folders
+            .iter()
+            .find(|folder| folder.name == "News")
+            .unwrap();
+        assert!(news.favorite);
+        assert_eq!(news.parent_id, None);
+        assert!(favorites
+            .folders
+            .iter()
+            .filter(|folder| folder.name != "News")
+            .all(|folder| !folder.favorite));
+
+        let folder =
+            parse_safari_fixture(SAFARI_BINARY, BookmarkBar::Folder, ReadingList::ReadLater);
+        assert!(paths(&folder).contains(&"Favorites/Apple".to_string()));
+        assert!(paths(&folder).contains(&"Favorites/News/Hacker News".to_string()));
+        assert!(folder.folders.iter().all(|folder| !folder.favorite));
+        assert!(folder.bookmarks.iter().all(|bookmark| !bookmark.favorite));
+    }
+
+    #[test]
+    fn safari_reading_list_becomes_read_later_items_or_a_folder() {
+        let read_later =
+            parse_safari_fixture(SAFARI_XML, BookmarkBar::Favorites, ReadingList::ReadLater);
+        let queued: Vec<&Bookmark> = read_later
+            .bookmarks
+            .iter()
+            .filter(|bookmark| bookmark.read_later)
+            .collect();
+        assert_eq!(queued.len(), 2);
+        let unread = queued
+            .iter()
+            .find(|bookmark| bookmark.name == "Long read")
+            .unwrap();
+        assert_eq!(unread.folder_id, None);
+        assert_eq!(unread.read_at, None);
+        assert_eq!(
+            unread.description.as_deref(),
+            Some("An article worth reading")
+        );
+        assert_eq!(
+            unread.created,
+            // 2024-01-02 03:04:05 UTC
+            OffsetDateTime::from_unix_timestamp(1704164645).unwrap()
+        );
+        let read = queued
+            .iter()
+            .find(|bookmark| bookmark.name == "Already read")
+            .unwrap();
+        assert!(read.read_at.is_some());
+
+        let folder = parse_safari_fixture(SAFARI_XML, BookmarkBar::Favorites, ReadingList::Folder);
+        assert!(paths(&folder).contains(&"Reading List/Long read".to_string()));
+        assert!(paths(&folder).contains(&"Reading List/Already read".to_string()));
+        assert!(folder.bookmarks.iter().all(|bookmark| !bookmark.read_later));
+    }
+
+    #[test]
+    fn merge_imports_add_nothing_the_second_time() {
+        for mode in [ImportMode::Merge, ImportMode::SkipDuplicates] {
+            let pool = database::test_pool();
+            let mut connection = pool.get().unwrap();
+            let query = import_query(mode);
+            assert_eq!(
+                import_html(&mut connection, &query, SAMPLE_HTML),
+                StatusCode::OK
+            );
+            assert_eq!(library_counts(&mut connection), (2, 3));
+
+            assert_eq!(
+                import_html(&mut connection, &query, SAMPLE_HTML),
+                StatusCode::OK
+            );
+            assert_eq!(library_counts(&mut connection), (2, 3), "{:?}", mode);
+        }
+    }
+
+    #[test]
+    fn only_skip_duplicates_looks_for_urls_in_other_folders() {
+        let moved_html = r#"<!DOCTYPE NETSCAPE-Bookmark-file-1>
+<DL><p>
+    <DT><H3>Elsewhere</H3>
+    <DL><p>
+        <DT><A HREF="https://example.com">Example</A>
+    </DL><p>
+</DL><p>
+"#;
+        for (mode, expected_bookmarks) in [(ImportMode::Merge, 4), (ImportMode::SkipDuplicates, 3)]
+        {
+            let pool = database::test_pool();
+            let mut connection = pool.get().unwrap();
+            import_html(&mut connection, &import_query(mode), SAMPLE_HTML);
+            import_html(&mut connection, &import_query(mode), moved_html);
+            assert_eq!(
+                library_counts(&mut connection),
+                (3, expected_bookmarks),
+                "{:?}",
+                mode
+            );
+        }
+    }
+
+    #[test]
+    fn dry_runs_leave_the_database_unchanged() {
+        let pool = database::test_pool();
+        let mut connection = pool.get().unwrap();
+        import_html(
+            &mut connection,
+            &import_query(ImportMode::Append),
+            SAMPLE_HTML,
+        );
+
+        for mode in [ImportMode::Append, ImportMode::Merge, ImportMode::Replace] {
+            let query = ImportQuery {
+                dry_run: true,
+                wrap: true,
+                ..import_query(mode)
+            };
+            assert_eq!(
+                import_html(&mut connection, &query, SAMPLE_HTML),
+                StatusCode::OK
+            );
+            assert_eq!(library_counts(&mut connection), (2, 3), "{:?}", mode);
+            let trash = database::get_trash(&mut connection, DEFAULT_LIBRARY_ID).unwrap();
+            assert!(trash.folders.is_empty() && trash.bookmarks.is_empty());
+        }
+    }
+
+    #[test]
+    fn imported_roots_go_under_the_target_or_wrapper_folder() {
+        let pool = database::test_pool();
+        let mut connection = pool.get().unwrap();
+        let target_id = database::create_new_folder(
+            &mut connection,
+            DEFAULT_LIBRARY_ID,
+            "Target".to_string(),
+            None,
+        )
+        .unwrap();
+        let children = |connection: &mut DbConnection, folder_id: Option<i32>| {
+            let folders =
+                database::get_all_child_folders(connection, DEFAULT_LIBRARY_ID, &folder_id)
+                    .unwrap();
+            let bookmarks =
+                database::get_all_child_bookmarks(connection, DEFAULT_LIBRARY_ID, &folder_id)
+                    .unwrap();
+            (
+                folders
+                    .into_iter()
+                    .map(|folder| folder.name)
+                    .collect::<Vec<_>>(),
+                bookmarks
+                    .into_iter()
+                    .map(|bookmark| bookmark.name)
+                    .collect::<Vec<_>>(),
+            )
+        };
+
+        let query = ImportQuery {
+            folder_id: Some(target_id),
+            ..import_query(ImportMode::Append)
+        };
+        import_html(&mut connection, &query, SAMPLE_HTML);
+        assert_eq!(
+            children(&mut connection, Some(target_id)),
+            (vec!["Dev".to_string()], vec!["Example".to_string()])
+        );
+
+        // The wrapper goes into the target folder too, next to the first import
+        let query = ImportQuery {
+            wrap: true,
+            ..query
+        };
+        import_html(&mut connection, &query, SAMPLE_HTML);
+        let (target_folders, _) = children(&mut connection, Some(target_id));
+        assert_eq!(target_folders.len(), 2);
+        assert!(target_folders[1].starts_with("Imported from HTML"));
+        let wrapper = database::get_all_folders(&mut connection, DEFAULT_LIBRARY_ID)
+            .unwrap()
+            .into_iter()
+            .find(|folder| folder.name == target_folders[1])
+            .unwrap();
+        assert_eq!(
+            children(&mut connection, Some(wrapper.id)),
+            (vec!["Dev".to_string()], vec!["Example".to_string()])
+        );
+        assert_eq!(
+            children(&mut connection, None).0,
+            vec!["Target".to_string()]
+        );
+    }
+
+    #[test]
+    fn interleaved_imports_and_creates_get_their_own_ids() {
+        let pool = database::test_pool();
+        let mut connection = pool.get().unwrap();
+        let query = import_query(ImportMode::Append);
+        let first = parse_bookmarks_html(&mut connection, DEFAULT_LIBRARY_ID, SAMPLE_HTML).unwrap();
+        let second =
+            parse_bookmarks_html(&mut connection, DEFAULT_LIBRARY_ID, SAMPLE_HTML).unwrap();
+        // Rows created between parsing and saving take the ids a guess would have used
+        let folder_id = database::create_new_folder(
+            &mut connection,
+            DEFAULT_LIBRARY_ID,
+            "Created".to_string(),
+            None,
+        )
+        .unwrap();
+        add_bookmark(
+            &mut connection,
+            NewBookmarkRequest {
+                folder_id: Some(folder_id),
+                ..new_bookmark("Created", "https://created.com")
+            },
+        );
+
+        assert_eq!(
+            save_import(&mut connection, &query, "HTML", second).status(),
+            StatusCode::OK
+        );
+        assert_eq!(
+            save_import(&mut connection, &query, "HTML", first).status(),
+            StatusCode::OK
+        );
+
+        let folders = database::get_all_folders(&mut connection, DEFAULT_LIBRARY_ID).unwrap();
+        let bookmarks = database::get_all_bookmarks(&mut connection, DEFAULT_LIBRARY_ID).unwrap();
+        assert_eq!((folders.len(), bookmarks.len()), (5, 7));
+        let folder_ids: HashSet<i32> = folders.iter().map(|folder| folder.id).collect();
+        assert_eq!(folder_ids.len(), folders.len());
+        // Every imported subfolder and bookmark points at a folder of its own import
+        for docs in folders.iter().filter(|folder| folder.name == "Docs") {
+            let dev = folders
+                .iter()
+                .find(|folder| Some(folder.id) == docs.parent_id)
+                .unwrap();
+            assert_eq!(dev.name, "Dev");
+            let docs_bookmarks: Vec<&Bookmark> = bookmarks
+                .iter()
+                .filter(|bookmark| bookmark.folder_id == Some(docs.id))
+                .collect();
+            assert_eq!(docs_bookmarks.len(), 1);
+            assert_eq!(docs_bookmarks[0].name, "Docs.rs");
+        }
+        let created = bookmarks
+            .iter()
+            .find(|bookmark| bookmark.name == "Created")
+            .unwrap();
+        assert_eq!(created.folder_id, Some(folder_id));
+        assert_eq!(
+            bookmarks
+                .iter()
+                .filter(|bookmark| bookmark.folder_id == Some(folder_id))
+                .count(),
+            1
+        );
     }
 }
@@ -0,0 +1,85 @@
+use std::sync::Arc;
+
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use diesel::result::{DatabaseErrorKind, Error};
+use serde_json::json;
+
+use crate::{
+    database::{self, Pool},
+    models::{NewLibraryRequest, RenameLibraryRequest, DEFAULT_LIBRARY_ID},
+};
+
+pub async fn list_libraries(State(pool): State<Arc<Pool>>) -> impl IntoResponse {
+    let mut connection = pool.get().expect("Failed to get connection from pool");
+    match database::get_all_libraries(&mut connection) {
+        Ok(libraries) => Ok(Json(libraries)),
+        Err(e) => {
+            eprintln!("Failed to fetch libraries: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+pub async fn create_library(
+    State(pool): State<Arc<Pool>>,
+    Json(payload): Json<NewLibraryRequest>,
+) -> impl IntoResponse {
+    if payload.name.trim().is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let mut connection = pool.get().expect("Failed to get connection from pool");
+    match database::create_library(&mut connection, &payload.name) {
+        Ok(id) => Ok(Json(json!({ "id": id }))),
+        Err(Error::DatabaseError(DatabaseErrorKind::UniqueViolation, _)) => {
+            Err(StatusCode::CONFLICT)
+        }
+        Err(e) => {
+            eprintln!("Failed to create library: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+pub async fn rename_library(
+    State(pool): State<Arc<Pool>>,
+    Json(payload): Json<RenameLibraryRequest>,
+) -> impl IntoResponse {
+    if payload.name.trim().is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let mut connection = pool.get().expect("Failed to get connection from pool");
+    match database::rename_library(&mut connection, payload.id, &payload.name) {
+        Ok(0) => Err(StatusCode::NOT_FOUND),
+        Ok(_) => Ok(StatusCode::OK),
+        Err(Error::DatabaseError(DatabaseErrorKind::UniqueViolation, _)) => {
+            Err(StatusCode::CONFLICT)
+        }
+        Err(e) => {
+            eprintln!("Failed to rename library: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Deletes a library with all of its folders and bookmarks
+pub async fn delete_library(
+    State(pool): State<Arc<Pool>>,
+    Json(payload): Json<i32>,
+) -> impl IntoResponse {
+    // Requests without a library fall back to the default one, so it has to stay
+    if payload == DEFAULT_LIBRARY_ID {
+        return Err(StatusCode::CONFLICT);
+    }
+
+    let mut connection = pool.get().expect("Failed to get connection from pool");
+    match database::delete_library(&mut connection, payload) {
+        Ok(0) => Err(StatusCode::NOT_FOUND),
+        Ok(_) => Ok(StatusCode::OK),
+        Err(e) => {
+            eprintln!("Failed to delete library: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
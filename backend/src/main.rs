@@ -2,11 +2,18 @@ mod create;
 mod database;
 mod drag_drop;
 mod export;
+mod favorites;
+mod history;
 mod import;
+mod libraries;
 mod models;
 mod modify;
+mod read_later;
 mod schema;
+mod tags;
+mod trash;
 mod tree;
+mod visits;
 
 use std::sync::Arc;
 
@@ -25,6 +32,11 @@ use tower_http::{
 async fn main() -> std::io::Result<()> {
     let pool = Arc::new(database::initialize_database());
 
+    let retention_days = trash::retention_days();
+    if retention_days > 0 {
+        tokio::spawn(trash::auto_purge(pool.clone(), retention_days));
+    }
+
     let static_path = std::env::var("STATIC_FILES_PATH").unwrap_or_else(|_| {
         // Development path
         #[cfg(debug_assertions)]
@@ -37,20 +49,73 @@ async fn main() -> std::io::Result<()> {
     let router =
         Router::new()
             .route("/api/tree", get(tree::refresh_tree))
+            .route("/api/libraries", get(libraries::list_libraries))
+            .route("/api/create-library", post(libraries::create_library))
+            .route("/api/rename-library", post(libraries::rename_library))
+            .route("/api/delete-library", post(libraries::delete_library))
+            .route("/api/bookmarks", get(visits::list_bookmarks))
+            .route("/api/stale-bookmarks", get(visits::stale_bookmarks))
+            .route("/go/:bookmark_id", get(visits::visit_bookmark))
             .route("/api/move", post(drag_drop::handle_move))
             .route("/api/import-html", post(import::import_bookmarks_html))
             .route(
                 "/api/import-linkwarden",
                 post(import::import_bookmarks_linkwarden),
             )
+            .route(
+                "/api/import-firefox",
+                post(import::import_bookmarks_firefox),
+            )
+            .route(
+                "/api/import-chromium",
+                post(import::import_bookmarks_chromium),
+            )
+            .route("/api/import-places", post(import::import_bookmarks_places))
+            .route(
+                "/api/import-pinboard",
+                post(import::import_bookmarks_pinboard),
+            )
+            .route("/api/import-pocket", post(import::import_bookmarks_pocket))
+            .route(
+                "/api/import-instapaper",
+                post(import::import_bookmarks_instapaper),
+            )
+            .route(
+                "/api/import-raindrop",
+                post(import::import_bookmarks_raindrop),
+            )
+            .route("/api/import-safari", post(import::import_bookmarks_safari))
             .route("/api/export", get(export::export_bookmarks))
+            .route("/api/export-xbel", get(export::export_bookmarks_xbel))
+            .route("/api/import-xbel", post(import::import_bookmarks_xbel))
+            .route("/api/export-opml", get(export::export_bookmarks_opml))
+            .route("/api/import-opml", post(import::import_bookmarks_opml))
             .route("/api/create-folder", post(create::create_folder))
             .route("/api/create-bookmark", post(create::create_bookmark))
             .route("/api/favorite-bookmark", post(modify::favorite_bookmark))
+            .route("/api/favorite-folder", post(modify::favorite_folder))
+            .route("/api/favorites", get(favorites::list_favorites))
+            .route("/api/reorder-favorites", post(favorites::reorder_favorites))
             .route("/api/update-folder", post(modify::update_folder))
             .route("/api/update-bookmark", post(modify::update_bookmark))
             .route("/api/delete-folder", post(modify::delete_folder))
             .route("/api/delete-bookmark", post(modify::delete_bookmark))
+            .route(
+                "/api/history/:item_type/:item_id",
+                get(history::item_history),
+            )
+            .route("/api/revert-history", post(history::revert_history))
+            .route("/api/trash", get(trash::list_trash))
+            .route("/api/restore-folder", post(trash::restore_folder))
+            .route("/api/restore-bookmark", post(trash::restore_bookmark))
+            .route("/api/empty-trash", post(trash::empty_trash))
+            .route("/api/read-later", get(read_later::list_read_later))
+            .route("/api/mark-read", post(read_later::mark_read))
+            .route("/api/mark-unread", post(read_later::mark_unread))
+            .route("/api/tags", get(tags::list_tags))
+            .route("/api/rename-tag", post(tags::rename_tag))
+            .route("/api/merge-tags", post(tags::merge_tags))
+            .route("/api/delete-tag", post(tags::delete_tag))
             .with_state(pool)
             .fallback_service(ServeDir::new(&static_path).not_found_service(
                 ServeDir::new(&static_path).append_index_html_on_directories(true),
@@ -1,5 +1,19 @@
+use std::cmp::Ordering;
+
 use diesel::prelude::*;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize, Serializer};
+
+/// The library everything belonged to before there were several, used when none is given
+pub const DEFAULT_LIBRARY_ID: i32 = 1;
+
+fn default_library_id() -> i32 {
+    DEFAULT_LIBRARY_ID
+}
+
+/// Stands in for fields missing from history snapshots taken before they existed
+fn unix_epoch() -> time::OffsetDateTime {
+    time::OffsetDateTime::UNIX_EPOCH
+}
 
 #[derive(Queryable, Selectable, Insertable)]
 #[diesel(table_name = crate::schema::folders)]
@@ -11,6 +25,13 @@ pub struct Folder {
     pub created: time::OffsetDateTime,
     pub parent_id: Option<i32>,
     pub favorite: bool,
+    pub position: i32,
+    pub favorite_position: i32,
+    pub deleted_at: Option<time::OffsetDateTime>,
+    #[serde(default = "unix_epoch")]
+    pub updated: time::OffsetDateTime,
+    #[serde(default = "default_library_id")]
+    pub library_id: i32,
 }
 
 #[derive(Queryable, Selectable, Insertable)]
@@ -26,12 +47,102 @@ pub struct Bookmark {
     pub created: time::OffsetDateTime,
     pub folder_id: Option<i32>,
     pub favorite: bool,
+    pub description: Option<String>,
+    pub position: i32,
+    pub favorite_position: i32,
+    pub deleted_at: Option<time::OffsetDateTime>,
+    #[serde(default)]
+    pub visit_count: i32,
+    pub last_visited: Option<time::OffsetDateTime>,
+    /// In the read-later queue, unread until `read_at` is set
+    #[serde(default)]
+    pub read_later: bool,
+    pub read_at: Option<time::OffsetDateTime>,
+    #[serde(default = "unix_epoch")]
+    pub updated: time::OffsetDateTime,
+    #[serde(default = "default_library_id")]
+    pub library_id: i32,
+    /// Browser keyword that opens the bookmark from the address bar
+    pub keyword: Option<String>,
+    /// When the bookmark was last put into the read-later queue
+    pub queued_at: Option<time::OffsetDateTime>,
+}
+
+impl Bookmark {
+    /// Visit count weighted by how recently the bookmark was last opened, like Firefox's frecency
+    pub fn frecency(&self, now: time::OffsetDateTime) -> i64 {
+        let Some(last_visited) = self.last_visited else {
+            return 0;
+        };
+        let weight = match (now - last_visited).whole_days() {
+            ..=4 => 100,
+            5..=14 => 70,
+            15..=31 => 50,
+            32..=90 => 30,
+            _ => 10,
+        };
+        i64::from(self.visit_count) * weight
+    }
+}
+
+#[derive(Queryable, Selectable)]
+#[diesel(table_name = crate::schema::separators)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Separator {
+    pub id: i32,
+    pub folder_id: Option<i32>,
+    /// Shared with the bookmarks in the folder, a separator comes before the bookmark
+    /// in the same position
+    pub position: i32,
+    pub library_id: i32,
+}
+
+#[derive(Queryable, Selectable)]
+#[diesel(table_name = crate::schema::libraries)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Library {
+    pub id: i32,
+    pub name: String,
+    pub created: time::OffsetDateTime,
+}
+
+#[derive(Queryable, Selectable)]
+#[diesel(table_name = crate::schema::tags)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Tag {
+    pub id: i32,
+    pub name: String,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = crate::schema::bookmark_tags)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct BookmarkTag {
+    pub bookmark_id: i32,
+    pub tag_id: i32,
+}
+
+#[derive(Queryable, Debug, Serialize)]
+pub struct TagSummary {
+    pub id: i32,
+    pub name: String,
+    pub bookmark_count: i64,
+}
+
+/// Items the user moved to the trash, without the children that were deleted along with them
+#[derive(Debug, Serialize)]
+pub struct TrashItems {
+    pub folders: Vec<Folder>,
+    pub bookmarks: Vec<Bookmark>,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct RootItems {
     pub root_folders: Vec<FolderNode>,
-    pub root_bookmarks: Vec<Bookmark>,
+    pub root_bookmarks: Vec<BookmarkNode>,
 }
 
 impl RootItems {
@@ -43,13 +154,39 @@ impl RootItems {
                 .cmp(&b.folder.name.to_lowercase())
         });
 
-        self.root_bookmarks
-            .sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+        self.root_bookmarks.sort_by(|a, b| {
+            a.bookmark
+                .name
+                .to_lowercase()
+                .cmp(&b.bookmark.name.to_lowercase())
+        });
 
         for folder in &mut self.root_folders {
             folder.sort_by_name();
         }
     }
+
+    pub fn sort_by_position(&mut self) {
+        self.root_folders
+            .sort_by_key(|node| (node.folder.position, node.folder.id));
+
+        self.root_bookmarks
+            .sort_by_key(|node| (node.bookmark.position, node.bookmark.id));
+
+        for folder in &mut self.root_folders {
+            folder.sort_by_position();
+        }
+    }
+
+    /// Reorders only the bookmarks, folders stay where they are
+    pub fn sort_bookmarks_by(&mut self, compare: &impl Fn(&Bookmark, &Bookmark) -> Ordering) {
+        self.root_bookmarks
+            .sort_by(|a, b| compare(&a.bookmark, &b.bookmark));
+
+        for folder in &mut self.root_folders {
+            folder.sort_bookmarks_by(compare);
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -57,7 +194,7 @@ pub struct FolderNode {
     #[serde(flatten)]
     pub folder: Folder,
     pub children: Vec<FolderNode>,
-    pub bookmarks: Vec<Bookmark>,
+    pub bookmarks: Vec<BookmarkNode>,
 }
 
 impl FolderNode {
@@ -69,27 +206,378 @@ impl FolderNode {
                 .cmp(&b.folder.name.to_lowercase())
         });
 
-        self.bookmarks
-            .sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+        self.bookmarks.sort_by(|a, b| {
+            a.bookmark
+                .name
+                .to_lowercase()
+                .cmp(&b.bookmark.name.to_lowercase())
+        });
 
         for child in &mut self.children {
             child.sort_by_name();
         }
     }
+
+    pub fn sort_by_position(&mut self) {
+        self.children
+            .sort_by_key(|node| (node.folder.position, node.folder.id));
+
+        self.bookmarks
+            .sort_by_key(|node| (node.bookmark.position, node.bookmark.id));
+
+        for child in &mut self.children {
+            child.sort_by_position();
+        }
+    }
+
+    pub fn sort_bookmarks_by(&mut self, compare: &impl Fn(&Bookmark, &Bookmark) -> Ordering) {
+        self.bookmarks
+            .sort_by(|a, b| compare(&a.bookmark, &b.bookmark));
+
+        for child in &mut self.children {
+            child.sort_bookmarks_by(compare);
+        }
+    }
 }
 
-#[derive(Debug, Deserialize, Serialize, PartialEq)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BookmarkNode {
+    #[serde(flatten)]
+    pub bookmark: Bookmark,
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone, Copy)]
 #[serde(rename_all = "lowercase")]
 pub enum ItemType {
     Folder,
     Bookmark,
 }
 
+impl ItemType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ItemType::Folder => "folder",
+            ItemType::Bookmark => "bookmark",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum HistoryAction {
+    Create,
+    Import,
+    Update,
+    Move,
+    Favorite,
+    Delete,
+    Restore,
+    Purge,
+    Revert,
+}
+
+impl HistoryAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HistoryAction::Create => "create",
+            HistoryAction::Import => "import",
+            HistoryAction::Update => "update",
+            HistoryAction::Move => "move",
+            HistoryAction::Favorite => "favorite",
+            HistoryAction::Delete => "delete",
+            HistoryAction::Restore => "restore",
+            HistoryAction::Purge => "purge",
+            HistoryAction::Revert => "revert",
+        }
+    }
+}
+
+/// One change to a folder or bookmark, with JSON snapshots of the item around it
+#[derive(Queryable, Selectable)]
+#[diesel(table_name = crate::schema::history)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+#[derive(Debug, Serialize)]
+pub struct HistoryEntry {
+    pub id: i32,
+    pub item_type: String,
+    pub item_id: i32,
+    pub action: String,
+    #[serde(serialize_with = "serialize_json_text")]
+    pub before_state: Option<String>,
+    #[serde(serialize_with = "serialize_json_text")]
+    pub after_state: Option<String>,
+    pub created: time::OffsetDateTime,
+}
+
+/// Snapshots are stored as text but sent to clients as JSON
+fn serialize_json_text<S: Serializer>(
+    text: &Option<String>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let value: Option<serde_json::Value> = text
+        .as_deref()
+        .and_then(|text| serde_json::from_str(text).ok());
+    value.serialize(serializer)
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(tag = "item_type", rename_all = "lowercase")]
+pub enum FavoriteItem {
+    Folder(FolderNode),
+    Bookmark(BookmarkNode),
+}
+
+impl FavoriteItem {
+    pub fn favorite_position(&self) -> i32 {
+        match self {
+            FavoriteItem::Folder(node) => node.folder.favorite_position,
+            FavoriteItem::Bookmark(node) => node.bookmark.favorite_position,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FavoriteRef {
+    pub item_type: ItemType,
+    pub item_id: i32,
+}
+
+#[derive(Debug, Default, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum SortOrder {
+    /// The user-defined order stored in `position`
+    #[default]
+    Manual,
+    Name,
+    Frecency,
+    MostVisited,
+    RecentlyVisited,
+}
+
+impl SortOrder {
+    /// Compares bookmarks for the visit based orders, most relevant first
+    pub fn compare_visits(
+        &self,
+        a: &Bookmark,
+        b: &Bookmark,
+        now: time::OffsetDateTime,
+    ) -> Ordering {
+        match self {
+            SortOrder::Frecency => b.frecency(now).cmp(&a.frecency(now)),
+            SortOrder::MostVisited => b.visit_count.cmp(&a.visit_count),
+            SortOrder::RecentlyVisited => b.last_visited.cmp(&a.last_visited),
+            SortOrder::Manual | SortOrder::Name => Ordering::Equal,
+        }
+        .then((a.position, a.id).cmp(&(b.position, b.id)))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TreeQuery {
+    #[serde(default = "default_library_id")]
+    pub library_id: i32,
+    #[serde(default)]
+    pub sort: SortOrder,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LibraryQuery {
+    #[serde(default = "default_library_id")]
+    pub library_id: i32,
+}
+
+/// How an import treats what's already in the library
+#[derive(Debug, Default, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ImportMode {
+    /// Add everything next to what's there
+    #[default]
+    Append,
+    /// Reuse folders with the same path and leave out URLs already in that folder
+    Merge,
+    /// Like merge, but leave out URLs that are anywhere in the library
+    SkipDuplicates,
+    /// Move what's there to the trash first
+    Replace,
+}
+
+/// Options every importer takes, next to the ones for its format
+#[derive(Debug, Deserialize)]
+pub struct ImportQuery {
+    #[serde(default = "default_library_id")]
+    pub library_id: i32,
+    #[serde(default)]
+    pub mode: ImportMode,
+    /// Only report what the import would do
+    #[serde(default)]
+    pub dry_run: bool,
+    /// Existing folder to import into instead of the top level
+    pub folder_id: Option<i32>,
+    /// Put everything in a new folder named after the source and the date
+    #[serde(default)]
+    pub wrap: bool,
+}
+
+/// What an import did, or would do on a dry run
+#[derive(Debug, Serialize)]
+pub struct ImportReport {
+    pub dry_run: bool,
+    pub imported: ImportCounts,
+    /// Duplicates plus entries that couldn't be read
+    pub skipped: usize,
+    /// Paths of the new folders, like `Dev/Rust`
+    pub folders: Vec<String>,
+    pub bookmarks: Vec<ImportedItem>,
+    /// Bookmarks left out because their URL was already there
+    pub duplicates: Vec<ImportedItem>,
+    /// Entries in the file that couldn't be read
+    pub invalid: Vec<String>,
+    /// Entries imported despite a problem, like a date that couldn't be read
+    pub warnings: Vec<String>,
+    /// The library's tree once the import is done
+    pub tree: TreeStats,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportCounts {
+    pub folders: usize,
+    pub bookmarks: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportedItem {
+    pub name: String,
+    pub url: String,
+    /// Path of the folder it goes in, none for the top level
+    pub folder: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TreeStats {
+    pub folders: usize,
+    pub bookmarks: usize,
+    /// How many folders deep the tree goes
+    pub depth: usize,
+}
+
+/// What to do with Firefox's built-in "menu", "toolbar", "unfiled" and "mobile" containers
+#[derive(Debug, Default, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum FirefoxRoots {
+    /// Their contents go straight to the top level
+    #[default]
+    Skip,
+    /// Each becomes a top-level folder
+    Folders,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FirefoxImportQuery {
+    #[serde(default)]
+    pub roots: FirefoxRoots,
+}
+
+/// Where the contents of a browser's bookmarks bar end up
+#[derive(Debug, Default, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum BookmarkBar {
+    /// At the top level and in the favorites bar
+    #[default]
+    Favorites,
+    /// In a regular top-level folder
+    Folder,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChromiumImportQuery {
+    #[serde(default)]
+    pub bookmark_bar: BookmarkBar,
+}
+
+/// Where Safari's Reading List ends up
+#[derive(Debug, Default, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ReadingList {
+    /// At the top level, saved to read later
+    #[default]
+    ReadLater,
+    /// In a regular top-level folder
+    Folder,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SafariImportQuery {
+    #[serde(default)]
+    pub bookmarks_bar: BookmarkBar,
+    #[serde(default)]
+    pub reading_list: ReadingList,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PinboardImportQuery {
+    /// Comma-separated tags that each become a folder, e.g. `?folder_tags=work,recipes`.
+    /// A bookmark goes into the folder of the first of these tags it has.
+    pub folder_tags: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BookmarkListQuery {
+    #[serde(default = "default_library_id")]
+    pub library_id: i32,
+    #[serde(default)]
+    pub sort: SortOrder,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StaleBookmarksQuery {
+    #[serde(default = "default_library_id")]
+    pub library_id: i32,
+    pub months: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReadLaterQuery {
+    #[serde(default = "default_library_id")]
+    pub library_id: i32,
+    #[serde(default)]
+    pub include_read: bool,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct MoveItemRequest {
     pub item_type: ItemType,
     pub item_id: i32,
     pub target_folder_id: Option<i32>,
+    /// Index among the target folder's items of the same type, appends when missing
+    pub target_index: Option<usize>,
+    /// Where a move to the top level goes, the item's own library when missing.
+    /// Ignored when there is a target folder, items always live in their folder's library.
+    pub target_library_id: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NewFolderRequest {
+    /// Only used at the top level, a folder inside another goes into its parent's library
+    #[serde(default = "default_library_id")]
+    pub library_id: i32,
+    pub name: String,
+    pub parent_id: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NewBookmarkRequest {
+    /// Only used at the top level, a bookmark inside a folder goes into the folder's library
+    #[serde(default = "default_library_id")]
+    pub library_id: i32,
+    pub name: String,
+    pub url: String,
+    pub folder_id: Option<i32>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub read_later: bool,
 }
 
 #[derive(Debug, Deserialize)]
@@ -105,6 +593,40 @@ pub struct UpdateBookmarkRequest {
     pub name: String,
     pub url: String,
     pub folder_id: Option<i32>,
+    pub tags: Option<Vec<String>>,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RenameTagRequest {
+    pub id: i32,
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MergeTagsRequest {
+    pub source_ids: Vec<i32>,
+    pub target_id: i32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NewLibraryRequest {
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RenameLibraryRequest {
+    pub id: i32,
+    pub name: String,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = crate::schema::separators)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct NewSeparator {
+    pub folder_id: Option<i32>,
+    pub position: i32,
+    pub library_id: i32,
 }
 
 #[derive(Insertable)]
@@ -118,6 +640,15 @@ pub struct NewBookmark {
     pub created: time::OffsetDateTime,
     pub folder_id: Option<i32>,
     pub favorite: bool,
+    pub description: Option<String>,
+    pub position: i32,
+    pub favorite_position: i32,
+    pub read_later: bool,
+    pub read_at: Option<time::OffsetDateTime>,
+    pub updated: time::OffsetDateTime,
+    pub library_id: i32,
+    pub keyword: Option<String>,
+    pub queued_at: Option<time::OffsetDateTime>,
 }
 
 #[derive(Insertable)]
@@ -128,4 +659,35 @@ pub struct NewFolder {
     pub created: time::OffsetDateTime,
     pub parent_id: Option<i32>,
     pub favorite: bool,
+    pub position: i32,
+    pub favorite_position: i32,
+    pub updated: time::OffsetDateTime,
+    pub library_id: i32,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = crate::schema::libraries)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct NewLibrary {
+    pub name: String,
+    pub created: time::OffsetDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = crate::schema::tags)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct NewTag {
+    pub name: String,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = crate::schema::history)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct NewHistoryEntry {
+    pub item_type: String,
+    pub item_id: i32,
+    pub action: String,
+    pub before_state: Option<String>,
+    pub after_state: Option<String>,
+    pub created: time::OffsetDateTime,
 }
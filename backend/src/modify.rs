@@ -20,6 +20,19 @@ pub async fn favorite_bookmark(
     Ok(StatusCode::OK)
 }
 
+pub async fn favorite_folder(
+    State(pool): State<Arc<Pool>>,
+    Json(payload): Json<i32>,
+) -> impl IntoResponse {
+    let mut connection = pool.get().expect("Failed to get connection from pool");
+    if let Err(e) = database::toggle_folder_favorite(&mut connection, payload) {
+        eprintln!("Failed to toggle favorite: {}", e);
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    Ok(StatusCode::OK)
+}
+
 pub async fn update_folder(
     State(pool): State<Arc<Pool>>,
     Json(payload): Json<UpdateFolderRequest>,
@@ -0,0 +1,72 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+
+use crate::{
+    database::{self, Pool},
+    models::{BookmarkNode, ReadLaterQuery},
+};
+
+/// The read-later queue in the order items were added, e.g. `?include_read=true`
+pub async fn list_read_later(
+    State(pool): State<Arc<Pool>>,
+    Query(query): Query<ReadLaterQuery>,
+) -> impl IntoResponse {
+    let mut connection = pool.get().expect("Failed to get connection from pool");
+    let queue =
+        match database::get_read_later(&mut connection, query.library_id, query.include_read) {
+            Ok(queue) => queue,
+            Err(e) => {
+                eprintln!("Failed to fetch read later queue: {}", e);
+                return Err(StatusCode::INTERNAL_SERVER_ERROR);
+            }
+        };
+    let mut bookmark_tags = match database::get_all_bookmark_tags(&mut connection) {
+        Ok(bookmark_tags) => bookmark_tags,
+        Err(e) => {
+            eprintln!("Failed to fetch bookmark tags: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    let queue: Vec<BookmarkNode> = queue
+        .into_iter()
+        .map(|bookmark| BookmarkNode {
+            tags: bookmark_tags.remove(&bookmark.id).unwrap_or_default(),
+            bookmark,
+        })
+        .collect();
+
+    Ok(Json(queue))
+}
+
+pub async fn mark_read(
+    State(pool): State<Arc<Pool>>,
+    Json(payload): Json<i32>,
+) -> impl IntoResponse {
+    let mut connection = pool.get().expect("Failed to get connection from pool");
+    if let Err(e) = database::mark_bookmark_read(&mut connection, payload) {
+        eprintln!("Failed to mark bookmark as read: {}", e);
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    Ok(StatusCode::OK)
+}
+
+pub async fn mark_unread(
+    State(pool): State<Arc<Pool>>,
+    Json(payload): Json<i32>,
+) -> impl IntoResponse {
+    let mut connection = pool.get().expect("Failed to get connection from pool");
+    if let Err(e) = database::mark_bookmark_unread(&mut connection, payload) {
+        eprintln!("Failed to mark bookmark as unread: {}", e);
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    Ok(StatusCode::OK)
+}
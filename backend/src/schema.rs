@@ -5,6 +5,11 @@ diesel::table! {
         created -> TimestamptzSqlite,
         parent_id -> Nullable<Integer>,
         favorite -> Bool,
+        position -> Integer,
+        favorite_position -> Integer,
+        deleted_at -> Nullable<TimestamptzSqlite>,
+        updated -> TimestamptzSqlite,
+        library_id -> Integer,
     }
 }
 
@@ -18,8 +23,74 @@ diesel::table! {
         created -> TimestamptzSqlite,
         folder_id -> Nullable<Integer>,
         favorite -> Bool,
+        description -> Nullable<Text>,
+        position -> Integer,
+        favorite_position -> Integer,
+        deleted_at -> Nullable<TimestamptzSqlite>,
+        visit_count -> Integer,
+        last_visited -> Nullable<TimestamptzSqlite>,
+        read_later -> Bool,
+        read_at -> Nullable<TimestamptzSqlite>,
+        updated -> TimestamptzSqlite,
+        library_id -> Integer,
+        keyword -> Nullable<Text>,
+        queued_at -> Nullable<TimestamptzSqlite>,
+    }
+}
+
+diesel::table! {
+    tags (id) {
+        id -> Integer,
+        name -> Text,
+    }
+}
+
+diesel::table! {
+    bookmark_tags (bookmark_id, tag_id) {
+        bookmark_id -> Integer,
+        tag_id -> Integer,
+    }
+}
+
+diesel::table! {
+    history (id) {
+        id -> Integer,
+        item_type -> Text,
+        item_id -> Integer,
+        action -> Text,
+        before_state -> Nullable<Text>,
+        after_state -> Nullable<Text>,
+        created -> TimestamptzSqlite,
+    }
+}
+
+diesel::table! {
+    libraries (id) {
+        id -> Integer,
+        name -> Text,
+        created -> TimestamptzSqlite,
+    }
+}
+
+diesel::table! {
+    separators (id) {
+        id -> Integer,
+        folder_id -> Nullable<Integer>,
+        position -> Integer,
+        library_id -> Integer,
     }
 }
 
 diesel::joinable!(bookmarks -> folders (folder_id));
-diesel::allow_tables_to_appear_in_same_query!(folders, bookmarks);
+diesel::joinable!(separators -> folders (folder_id));
+diesel::joinable!(bookmark_tags -> bookmarks (bookmark_id));
+diesel::joinable!(bookmark_tags -> tags (tag_id));
+diesel::allow_tables_to_appear_in_same_query!(
+    folders,
+    bookmarks,
+    tags,
+    bookmark_tags,
+    history,
+    libraries,
+    separators
+);
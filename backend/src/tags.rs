@@ -0,0 +1,79 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use diesel::result::{DatabaseErrorKind, Error};
+
+use crate::{
+    database::{self, Pool},
+    models::{LibraryQuery, MergeTagsRequest, RenameTagRequest},
+};
+
+pub async fn list_tags(
+    State(pool): State<Arc<Pool>>,
+    Query(query): Query<LibraryQuery>,
+) -> impl IntoResponse {
+    let mut connection = pool.get().expect("Failed to get connection from pool");
+    match database::get_all_tags(&mut connection, query.library_id) {
+        Ok(tags) => Ok(Json(tags)),
+        Err(e) => {
+            eprintln!("Failed to fetch tags: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+pub async fn rename_tag(
+    State(pool): State<Arc<Pool>>,
+    Json(payload): Json<RenameTagRequest>,
+) -> impl IntoResponse {
+    if payload.name.trim().is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let mut connection = pool.get().expect("Failed to get connection from pool");
+    match database::rename_tag(&mut connection, payload.id, &payload.name) {
+        Ok(0) => Err(StatusCode::NOT_FOUND),
+        Ok(_) => Ok(StatusCode::OK),
+        // Another tag already has this name, the client should merge instead
+        Err(Error::DatabaseError(DatabaseErrorKind::UniqueViolation, _)) => {
+            Err(StatusCode::CONFLICT)
+        }
+        Err(e) => {
+            eprintln!("Failed to rename tag: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+pub async fn merge_tags(
+    State(pool): State<Arc<Pool>>,
+    Json(payload): Json<MergeTagsRequest>,
+) -> impl IntoResponse {
+    let mut connection = pool.get().expect("Failed to get connection from pool");
+    match database::merge_tags(&mut connection, payload.source_ids, payload.target_id) {
+        Ok(_) => Ok(StatusCode::OK),
+        Err(Error::NotFound) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            eprintln!("Failed to merge tags: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+pub async fn delete_tag(
+    State(pool): State<Arc<Pool>>,
+    Json(payload): Json<i32>,
+) -> impl IntoResponse {
+    let mut connection = pool.get().expect("Failed to get connection from pool");
+    if let Err(e) = database::delete_tag(&mut connection, payload) {
+        eprintln!("Failed to delete tag: {}", e);
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    Ok(StatusCode::OK)
+}
@@ -0,0 +1,107 @@
+use std::{sync::Arc, time::Duration};
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use diesel::result::Error;
+use serde_json::json;
+
+use crate::{
+    database::{self, Pool},
+    models::LibraryQuery,
+};
+
+const PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+pub async fn list_trash(
+    State(pool): State<Arc<Pool>>,
+    Query(query): Query<LibraryQuery>,
+) -> impl IntoResponse {
+    let mut connection = pool.get().expect("Failed to get connection from pool");
+    match database::get_trash(&mut connection, query.library_id) {
+        Ok(trash) => Ok(Json(trash)),
+        Err(e) => {
+            eprintln!("Failed to fetch trash: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+pub async fn restore_folder(
+    State(pool): State<Arc<Pool>>,
+    Json(payload): Json<i32>,
+) -> impl IntoResponse {
+    let mut connection = pool.get().expect("Failed to get connection from pool");
+    match database::restore_folder(&mut connection, payload) {
+        Ok(_) => Ok(StatusCode::OK),
+        Err(Error::NotFound) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            eprintln!("Failed to restore folder: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+pub async fn restore_bookmark(
+    State(pool): State<Arc<Pool>>,
+    Json(payload): Json<i32>,
+) -> impl IntoResponse {
+    let mut connection = pool.get().expect("Failed to get connection from pool");
+    match database::restore_bookmark(&mut connection, payload) {
+        Ok(_) => Ok(StatusCode::OK),
+        Err(Error::NotFound) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            eprintln!("Failed to restore bookmark: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+pub async fn empty_trash(
+    State(pool): State<Arc<Pool>>,
+    Query(query): Query<LibraryQuery>,
+) -> impl IntoResponse {
+    let mut connection = pool.get().expect("Failed to get connection from pool");
+    match database::purge_trash(&mut connection, Some(query.library_id), None) {
+        Ok(purged) => Ok(Json(json!({ "purged": purged }))),
+        Err(e) => {
+            eprintln!("Failed to empty trash: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Number of days items stay in the trash, set with TRASH_RETENTION_DAYS. Without it, or with
+/// 0, they are kept until the trash is emptied.
+pub fn retention_days() -> u32 {
+    std::env::var("TRASH_RETENTION_DAYS")
+        .ok()
+        .and_then(|days| days.trim().parse().ok())
+        .unwrap_or(0)
+}
+
+/// Periodically deletes items that have been in the trash longer than `days`
+pub async fn auto_purge(pool: Arc<Pool>, days: u32) {
+    let mut interval = tokio::time::interval(PURGE_INTERVAL);
+    loop {
+        interval.tick().await;
+
+        let cutoff = time::OffsetDateTime::now_utc() - time::Duration::days(days.into());
+        let mut connection = match pool.get() {
+            Ok(connection) => connection,
+            Err(e) => {
+                eprintln!("Failed to get connection for trash purge: {}", e);
+                continue;
+            }
+        };
+        // Retention applies to every library
+        match database::purge_trash(&mut connection, None, Some(cutoff)) {
+            Ok(0) => {}
+            Ok(purged) => println!("Purged {} items from the trash", purged),
+            Err(e) => eprintln!("Failed to purge trash: {}", e),
+        }
+    }
+}
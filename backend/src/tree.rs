@@ -1,29 +1,46 @@
 use std::{collections::HashMap, sync::Arc};
 
-use axum::{extract::State, response::IntoResponse, Json};
+use axum::{
+    extract::{Query, State},
+    response::IntoResponse,
+    Json,
+};
 
 use crate::{
-    database::{self, Pool},
-    models::{Bookmark, FolderNode, RootItems},
+    database::{self, DbConnection, Pool},
+    models::{BookmarkNode, FolderNode, RootItems, SortOrder, TreeQuery},
 };
 
-pub async fn refresh_tree(State(pool): State<Arc<Pool>>) -> impl IntoResponse {
+pub async fn refresh_tree(
+    State(pool): State<Arc<Pool>>,
+    Query(query): Query<TreeQuery>,
+) -> impl IntoResponse {
     let mut connection = pool.get().expect("Failed to get connection from pool");
+    Json(build_tree(&mut connection, query.library_id, query.sort)).into_response()
+}
 
-    let folders = match database::get_all_folders(&mut connection) {
+pub fn build_tree(connection: &mut DbConnection, library_id: i32, sort: SortOrder) -> RootItems {
+    let folders = match database::get_all_folders(connection, library_id) {
         Ok(items) => items,
         Err(e) => {
             eprintln!("Error fetching folders: {}", e);
             vec![]
         }
     };
-    let bookmarks = match database::get_all_bookmarks(&mut connection) {
+    let bookmarks = match database::get_all_bookmarks(connection, library_id) {
         Ok(items) => items,
         Err(e) => {
             eprintln!("Error fetching folders: {}", e);
             vec![]
         }
     };
+    let mut bookmark_tags = match database::get_all_bookmark_tags(connection) {
+        Ok(tags) => tags,
+        Err(e) => {
+            eprintln!("Error fetching tags: {}", e);
+            HashMap::new()
+        }
+    };
 
     let mut folder_map: HashMap<i32, FolderNode> = folders
         .iter()
@@ -39,12 +56,20 @@ pub async fn refresh_tree(State(pool): State<Arc<Pool>>) -> impl IntoResponse {
         })
         .collect();
 
-    let mut root_bookmarks: Vec<Bookmark> = Vec::new();
+    let mut root_bookmarks: Vec<BookmarkNode> = Vec::new();
 
     for bookmark in bookmarks {
-        match bookmark.folder_id.and_then(|id| folder_map.get_mut(&(id))) {
-            Some(folder) => folder.bookmarks.push(bookmark.clone()),
-            None => root_bookmarks.push(bookmark.clone()),
+        let bookmark_node = BookmarkNode {
+            tags: bookmark_tags.remove(&bookmark.id).unwrap_or_default(),
+            bookmark,
+        };
+        match bookmark_node
+            .bookmark
+            .folder_id
+            .and_then(|id| folder_map.get_mut(&(id)))
+        {
+            Some(folder) => folder.bookmarks.push(bookmark_node),
+            None => root_bookmarks.push(bookmark_node),
         }
     }
 
@@ -67,9 +92,18 @@ pub async fn refresh_tree(State(pool): State<Arc<Pool>>) -> impl IntoResponse {
         root_folders: folder_map.values().cloned().collect(),
         root_bookmarks,
     };
-    root_items.sort_by_name();
+    match sort {
+        SortOrder::Manual => root_items.sort_by_position(),
+        SortOrder::Name => root_items.sort_by_name(),
+        // Folders have no visits, so they keep their manual order
+        SortOrder::Frecency | SortOrder::MostVisited | SortOrder::RecentlyVisited => {
+            let now = time::OffsetDateTime::now_utc();
+            root_items.sort_by_position();
+            root_items.sort_bookmarks_by(&|a, b| sort.compare_visits(a, b, now));
+        }
+    }
 
-    Json(root_items).into_response()
+    root_items
 }
 
 fn traverse_folder_nodes(folder_node: &mut FolderNode, child_node: &FolderNode) {
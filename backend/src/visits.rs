@@ -0,0 +1,123 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Redirect},
+    Json,
+};
+
+use crate::{
+    database::{self, Pool},
+    models::{BookmarkListQuery, BookmarkNode, SortOrder, StaleBookmarksQuery},
+};
+
+const DEFAULT_STALE_MONTHS: u32 = 6;
+
+/// Counts the visit, then sends the browser on to the bookmark's URL
+pub async fn visit_bookmark(
+    State(pool): State<Arc<Pool>>,
+    Path(bookmark_id): Path<i32>,
+) -> impl IntoResponse {
+    let mut connection = pool.get().expect("Failed to get connection from pool");
+    match database::record_visit(&mut connection, bookmark_id) {
+        // Temporary so browsers don't cache the redirect and skip counting the next visit
+        Ok(Some(url)) => Ok(Redirect::temporary(&url)),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            eprintln!("Failed to record visit: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// All bookmarks as a flat list, e.g. `?sort=most-visited&limit=10`
+pub async fn list_bookmarks(
+    State(pool): State<Arc<Pool>>,
+    Query(query): Query<BookmarkListQuery>,
+) -> impl IntoResponse {
+    let mut connection = pool.get().expect("Failed to get connection from pool");
+    let mut bookmarks = match load_bookmark_nodes(&mut connection, query.library_id) {
+        Ok(bookmarks) => bookmarks,
+        Err(e) => {
+            eprintln!("Failed to fetch bookmarks: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    match query.sort {
+        SortOrder::Manual => {
+            bookmarks.sort_by_key(|node| (node.bookmark.position, node.bookmark.id));
+        }
+        SortOrder::Name => bookmarks.sort_by(|a, b| {
+            a.bookmark
+                .name
+                .to_lowercase()
+                .cmp(&b.bookmark.name.to_lowercase())
+        }),
+        SortOrder::Frecency | SortOrder::MostVisited | SortOrder::RecentlyVisited => {
+            let now = time::OffsetDateTime::now_utc();
+            bookmarks.sort_by(|a, b| query.sort.compare_visits(&a.bookmark, &b.bookmark, now));
+        }
+    }
+    if let Some(limit) = query.limit {
+        bookmarks.truncate(limit);
+    }
+
+    Ok(Json(bookmarks))
+}
+
+/// Bookmarks nobody has opened in the last `months` months, longest forgotten first.
+/// Bookmarks that were never opened count from the day they were added.
+pub async fn stale_bookmarks(
+    State(pool): State<Arc<Pool>>,
+    Query(query): Query<StaleBookmarksQuery>,
+) -> impl IntoResponse {
+    let mut connection = pool.get().expect("Failed to get connection from pool");
+    let bookmarks = match load_bookmark_nodes(&mut connection, query.library_id) {
+        Ok(bookmarks) => bookmarks,
+        Err(e) => {
+            eprintln!("Failed to fetch bookmarks: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    let cutoff = months_ago(
+        time::OffsetDateTime::now_utc(),
+        query.months.unwrap_or(DEFAULT_STALE_MONTHS),
+    );
+    let mut stale: Vec<BookmarkNode> = bookmarks
+        .into_iter()
+        .filter(|node| node.bookmark.last_visited.unwrap_or(node.bookmark.created) < cutoff)
+        .collect();
+    stale.sort_by_key(|node| node.bookmark.last_visited.unwrap_or(node.bookmark.created));
+
+    Ok(Json(stale))
+}
+
+fn load_bookmark_nodes(
+    connection: &mut database::DbConnection,
+    library_id: i32,
+) -> Result<Vec<BookmarkNode>, diesel::result::Error> {
+    let mut bookmark_tags = database::get_all_bookmark_tags(connection)?;
+    Ok(database::get_all_bookmarks(connection, library_id)?
+        .into_iter()
+        .map(|bookmark| BookmarkNode {
+            tags: bookmark_tags.remove(&bookmark.id).unwrap_or_default(),
+            bookmark,
+        })
+        .collect())
+}
+
+/// The same day `months` calendar months earlier, clamped to the end of shorter months
+fn months_ago(now: time::OffsetDateTime, months: u32) -> time::OffsetDateTime {
+    let total_months = now.year() * 12 + i32::from(u8::from(now.month())) - 1 - months as i32;
+    let year = total_months.div_euclid(12);
+    let month = time::Month::try_from((total_months.rem_euclid(12) + 1) as u8)
+        .unwrap_or(time::Month::January);
+    let day = now.day().min(month.length(year));
+
+    time::Date::from_calendar_date(year, month, day)
+        .map(|date| now.replace_date(date))
+        .unwrap_or(now)
+}
@@ -7,18 +7,44 @@ export interface Bookmark {
 	created: string;
 	folder_id: number | null;
 	favorite: boolean;
+	tags: string[];
+	description: string | null;
+	position: number;
+	favorite_position: number;
+	visit_count: number;
+	last_visited: string | null;
+	read_later: boolean;
+	read_at: string | null;
+	updated: string;
+	library_id: number;
+}
+
+export interface Library {
+	id: number;
+	name: string;
+	created: string;
 }
 
 export interface Folder {
 	id: number;
 	name: string;
 	parent_id: number | null;
+	favorite: boolean;
+	position: number;
+	favorite_position: number;
+	updated: string;
+	library_id: number;
 }
 
 export interface FolderNode {
 	id: number;
 	name: string;
 	parent_id: number | null;
+	favorite: boolean;
+	position: number;
+	favorite_position: number;
+	updated: string;
+	library_id: number;
 	children: FolderNode[];
 	bookmarks: Bookmark[];
 }
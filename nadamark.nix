@@ -15,6 +15,8 @@
         # Replace with your user's UID/GID (find with 'id -u' and 'id -g' commands)
         USER_ID = "1000";
         GROUP_ID = "1000";
+        # Uncomment to remove deleted items from the trash after this many days (kept forever by default)
+        # TRASH_RETENTION_DAYS = "30";
       };
   };
 